@@ -0,0 +1,164 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::buffs::BuffType;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum AbilityType {
+    Hack,
+    Sweep,
+    Overclock,
+    Defrag,
+    Fortify,
+    Purge,
+}
+
+impl AbilityType {
+    pub fn name(&self) -> &'static str {
+        ability_def(self).name
+    }
+}
+
+impl fmt::Display for AbilityType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Who an ability's damage lands on; buffs and healing always go to the player
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Targeting {
+    /// the contact the player has picked
+    OneContact,
+    /// every contact, splitting the damage between them
+    AllContacts,
+    /// no contact at all
+    Player,
+}
+
+/// What using an ability does
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AbilityEffect {
+    /// a hack's worth of damage, scaled
+    Damage(f32),
+    Buff(BuffType, u32),
+    Heal(i32),
+    /// strip a buff off the player
+    Purge(BuffType),
+}
+
+impl AbilityEffect {
+    pub fn description(&self) -> String {
+        match self {
+            AbilityEffect::Damage(scale) if *scale == 1.0 => "hack damage".to_string(),
+            AbilityEffect::Damage(scale) => format!("{}% hack damage", (scale * 100.0) as i32),
+            AbilityEffect::Buff(buff, turns) => format!("{} for {} turns", buff.name(), turns),
+            AbilityEffect::Heal(amount) => format!("+{} HP", amount),
+            AbilityEffect::Purge(buff) => format!("removes {}", buff.name()),
+        }
+    }
+}
+
+/// Everything combat needs to know about an ability
+pub struct AbilityDef {
+    pub ability: AbilityType,
+    pub name: &'static str,
+    pub ram_cost: i32,
+    /// turns after using it before it can be used again
+    pub cooldown: u32,
+    pub targeting: Targeting,
+    pub effects: &'static [AbilityEffect],
+    /// contacts take their turn afterwards; otherwise the player gets to act again
+    pub ends_round: bool,
+}
+
+impl AbilityDef {
+    pub fn description(&self) -> String {
+        let effects = self
+            .effects
+            .iter()
+            .map(|effect| effect.description())
+            .collect::<Vec<_>>()
+            .join(", ");
+        match self.targeting {
+            Targeting::OneContact => format!("{} to one contact", effects),
+            Targeting::AllContacts => format!("{}, split between every contact", effects),
+            Targeting::Player => effects,
+        }
+    }
+}
+
+/// What the combat bar can hold; a player only gets the ones in their loadout
+pub const ABILITIES: &[AbilityDef] = &[
+    AbilityDef {
+        ability: AbilityType::Hack,
+        name: "Launch Hack",
+        ram_cost: 4,
+        cooldown: 0,
+        targeting: Targeting::OneContact,
+        effects: &[AbilityEffect::Damage(1.0)],
+        ends_round: true,
+    },
+    AbilityDef {
+        ability: AbilityType::Sweep,
+        name: "Sweep",
+        ram_cost: 8,
+        cooldown: 0,
+        targeting: Targeting::AllContacts,
+        effects: &[AbilityEffect::Damage(1.0)],
+        ends_round: true,
+    },
+    AbilityDef {
+        ability: AbilityType::Overclock,
+        name: "Overclock Systems",
+        ram_cost: 10,
+        cooldown: 1,
+        targeting: Targeting::Player,
+        effects: &[AbilityEffect::Buff(BuffType::Overclock, 1)],
+        ends_round: false,
+    },
+    AbilityDef {
+        ability: AbilityType::Defrag,
+        name: "Defrag",
+        ram_cost: 12,
+        cooldown: 3,
+        targeting: Targeting::Player,
+        effects: &[AbilityEffect::Heal(20)],
+        ends_round: true,
+    },
+    AbilityDef {
+        ability: AbilityType::Fortify,
+        name: "Fortify Firewall",
+        ram_cost: 8,
+        cooldown: 3,
+        targeting: Targeting::Player,
+        // halves incoming damage through the contacts' next two turns
+        effects: &[AbilityEffect::Buff(BuffType::FirewallFortified, 2)],
+        ends_round: true,
+    },
+    AbilityDef {
+        ability: AbilityType::Purge,
+        name: "Purge Malware",
+        ram_cost: 6,
+        cooldown: 2,
+        targeting: Targeting::Player,
+        effects: &[AbilityEffect::Purge(BuffType::MalwareInjected)],
+        ends_round: true,
+    },
+];
+
+/// what a new runner can do in a fight
+pub const STARTING_ABILITIES: &[AbilityType] = &[
+    AbilityType::Hack,
+    AbilityType::Sweep,
+    AbilityType::Overclock,
+    AbilityType::Fortify,
+    AbilityType::Purge,
+];
+
+pub fn ability_def(ability: &AbilityType) -> &'static AbilityDef {
+    ABILITIES
+        .iter()
+        .find(|def| def.ability == *ability)
+        .expect("every ability type has a definition")
+}
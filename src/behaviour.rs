@@ -0,0 +1,203 @@
+use rand::{seq::SliceRandom, Rng};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::pieces::Networks;
+
+/// contacts in one fight, summoned sub-processes included
+pub const MAX_CONTACTS: usize = 4;
+
+/// Moves a contact can make on its turn
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ContactAbility {
+    /// the plain retaliation, hacking against the player's security
+    Strike,
+    /// weakens the player's hacks and makes them cost more RAM
+    InjectMalware,
+    /// halves the damage the contact takes for a couple of turns
+    Fortify,
+    DrainRam,
+    /// brings a weaker copy of itself into the fight
+    SummonSubprocess,
+    SelfRepair,
+}
+
+/// The move a contact has lined up for its next turn, shown to the player ahead of time
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Intent {
+    pub ability: ContactAbility,
+    /// damage, RAM drained or HP repaired, rolled when the move is picked
+    pub amount: i32,
+}
+
+impl fmt::Display for Intent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.ability {
+            ContactAbility::Strike => write!(f, "charging {} dmg", self.amount),
+            ContactAbility::InjectMalware => write!(f, "about to inject malware"),
+            ContactAbility::Fortify => write!(f, "fortifying its firewall"),
+            ContactAbility::DrainRam => write!(f, "draining {} RAM", self.amount),
+            ContactAbility::SummonSubprocess => write!(f, "spawning a sub-process"),
+            ContactAbility::SelfRepair => write!(f, "patching itself up (+{} HP)", self.amount),
+        }
+    }
+}
+
+/// How a contact picks between its abilities
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Behaviour {
+    /// hits hard and infects
+    Aggressive,
+    /// digs in and patches itself up
+    Defensive,
+    /// bleeds the player's RAM
+    Parasitic,
+    /// spreads copies of itself
+    Swarming,
+}
+
+/// What a contact can see when it decides what to do
+pub struct Situation {
+    /// share of its own HP left, 0.0 to 1.0
+    pub hp_left: f32,
+    pub fortified: bool,
+    pub player_infected: bool,
+    pub player_ram: i32,
+    pub room_to_summon: bool,
+}
+
+impl Behaviour {
+    /// how much this behaviour favours `ability` right now, 0 rules it out
+    fn weight(&self, ability: ContactAbility, situation: &Situation) -> u32 {
+        match ability {
+            ContactAbility::Strike => match self {
+                Behaviour::Aggressive => 6,
+                Behaviour::Swarming => 4,
+                Behaviour::Defensive | Behaviour::Parasitic => 3,
+            },
+            ContactAbility::InjectMalware if situation.player_infected => 0,
+            ContactAbility::InjectMalware => match self {
+                Behaviour::Aggressive => 3,
+                _ => 1,
+            },
+            ContactAbility::Fortify if situation.fortified => 0,
+            ContactAbility::Fortify => match self {
+                Behaviour::Defensive => 4,
+                _ => 1,
+            },
+            ContactAbility::DrainRam if situation.player_ram <= 0 => 0,
+            ContactAbility::DrainRam => match self {
+                Behaviour::Parasitic => 4,
+                _ => 1,
+            },
+            ContactAbility::SummonSubprocess if !situation.room_to_summon => 0,
+            ContactAbility::SummonSubprocess => match self {
+                Behaviour::Swarming => 3,
+                _ => 1,
+            },
+            ContactAbility::SelfRepair if situation.hp_left >= 0.5 => 0,
+            ContactAbility::SelfRepair => match self {
+                Behaviour::Defensive => 4,
+                Behaviour::Parasitic => 3,
+                _ => 1,
+            },
+        }
+    }
+
+    /// pick one of `abilities` for this turn, a plain strike if none of them fit
+    pub fn choose(
+        &self,
+        abilities: &[ContactAbility],
+        situation: &Situation,
+        rng: &mut impl Rng,
+    ) -> ContactAbility {
+        abilities
+            .choose_weighted(rng, |ability| self.weight(*ability, situation))
+            .copied()
+            .unwrap_or(ContactAbility::Strike)
+    }
+}
+
+/// A kind of program, deciding what a contact can do and how it goes about it
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Archetype {
+    Malware,
+    Swarm,
+    Leech,
+    /// government intrusion countermeasures
+    Ice,
+    Tracer,
+    /// spawned mid-fight by a swarm
+    Subprocess,
+}
+
+impl Archetype {
+    pub fn abilities(&self) -> &'static [ContactAbility] {
+        match self {
+            Archetype::Malware => &[ContactAbility::Strike, ContactAbility::InjectMalware],
+            Archetype::Swarm => &[ContactAbility::Strike, ContactAbility::SummonSubprocess],
+            Archetype::Leech => &[
+                ContactAbility::Strike,
+                ContactAbility::DrainRam,
+                ContactAbility::SelfRepair,
+            ],
+            Archetype::Ice => &[
+                ContactAbility::Strike,
+                ContactAbility::Fortify,
+                ContactAbility::SelfRepair,
+            ],
+            Archetype::Tracer => &[
+                ContactAbility::Strike,
+                ContactAbility::DrainRam,
+                ContactAbility::InjectMalware,
+            ],
+            Archetype::Subprocess => &[ContactAbility::Strike],
+        }
+    }
+
+    pub fn behaviour(&self) -> Behaviour {
+        match self {
+            Archetype::Malware | Archetype::Subprocess => Behaviour::Aggressive,
+            Archetype::Swarm => Behaviour::Swarming,
+            Archetype::Leech | Archetype::Tracer => Behaviour::Parasitic,
+            Archetype::Ice => Behaviour::Defensive,
+        }
+    }
+}
+
+impl fmt::Display for Archetype {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// Who the player can run into on the open internet, and how each one fights
+const INTERNET_CONTACTS: &[(&str, Archetype)] = &[
+    ("adware-imp", Archetype::Swarm),
+    ("maniabot", Archetype::Malware),
+    ("SpamSpyder", Archetype::Swarm),
+    ("darknet-dragon", Archetype::Malware),
+    ("silent-strike", Archetype::Leech),
+    ("phantom_protocol", Archetype::Leech),
+];
+
+/// Every hostile guarding SIPRnet
+const SIPR_CONTACTS: &[(&str, Archetype)] = &[
+    ("VigilanceCore", Archetype::Ice),
+    ("PulseDefender", Archetype::Ice),
+    ("AegisProtocol", Archetype::Ice),
+    ("CyberSam", Archetype::Tracer),
+    ("ApexSentinel", Archetype::Ice),
+    ("EtherealVigil", Archetype::Tracer),
+    ("QuantumSafeguard", Archetype::Tracer),
+];
+
+/// a random hostile for `net`, with the archetype it fights as
+pub fn random_hostile(net: &Networks, rng: &mut impl Rng) -> (String, Archetype) {
+    let table = match net {
+        Networks::Internet => INTERNET_CONTACTS,
+        Networks::SIPRnet => SIPR_CONTACTS,
+    };
+    let (name, archetype) = table.choose(rng).unwrap();
+    (name.to_string(), *archetype)
+}
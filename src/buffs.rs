@@ -1,10 +1,23 @@
-#[derive(PartialEq)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum BuffType {
     MalwareInjected,
     FirewallFortified,
     Overclock,
 }
 
+impl BuffType {
+    pub fn name(&self) -> String {
+        match *self {
+            BuffType::MalwareInjected => "Malware Injected".to_string(),
+            BuffType::FirewallFortified => "Firewall Fortified".to_string(),
+            BuffType::Overclock => "Overclock".to_string(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
 pub struct Buff {
     buff_type: BuffType,
     duration: u32,
@@ -33,6 +46,7 @@ impl Buff {
     }
 }
 
+#[derive(Default, Serialize, Deserialize)]
 pub struct BuffContainer {
     buffs: Vec<Buff>,
 }
@@ -50,12 +64,18 @@ impl BuffContainer {
         }
     }
 
+    /// take a buff off early, true if it was there
+    pub fn remove_buff(&mut self, buff_type: BuffType) -> bool {
+        let before = self.buffs.len();
+        self.buffs.retain(|buff| buff.buff_type != buff_type);
+        self.buffs.len() != before
+    }
+
     pub fn has_buff(&self, buff_type: BuffType) -> Option<u32> {
-        if let Some(buff) = self.buffs.iter().find(|b| b.buff_type == buff_type) {
-            Some(buff.duration)
-        } else {
-            None
-        }
+        self.buffs
+            .iter()
+            .find(|b| b.buff_type == buff_type)
+            .map(|buff| buff.duration)
     }
 
     pub fn do_turn(&mut self) {
@@ -77,22 +97,102 @@ impl BuffContainer {
                 BuffType::Overclock => multiplier += 1.1,
             }
         }
-        return (base_dmg as f32 * multiplier).ceil() as i32;
+        (base_dmg as f32 * multiplier).ceil() as i32
     }
 
-    pub fn get_buff_ram(&self, base_ram: i32) -> i32 {
-        let mut multiplier = 1.0;
+    /// RAM an ability takes to run; only malware gets in the way, overclocking is already paid for
+    pub fn get_ability_ram(&self, base_ram: i32) -> i32 {
+        let mut multiplier: f32 = 1.0;
+        if self.has_buff(BuffType::MalwareInjected).is_some() {
+            multiplier += 0.2;
+        }
+        (base_ram as f32 * multiplier).ceil() as i32
+    }
+
+    /// what's left of an incoming hit once the buffs have absorbed their share
+    pub fn get_dmg_taken(&self, incoming_dmg: i32) -> i32 {
+        let mut multiplier: f32 = 1.0;
         for buff in self.buffs.iter() {
             match buff.buff_type {
-                BuffType::MalwareInjected => multiplier += 0.2,
-                BuffType::FirewallFortified => {}
-                BuffType::Overclock => multiplier += 0.9,
+                BuffType::MalwareInjected => {}
+                BuffType::FirewallFortified => multiplier -= 0.5,
+                BuffType::Overclock => {}
             }
         }
-        return (base_ram as f32 * multiplier).ceil() as i32;
+        (incoming_dmg as f32 * multiplier.max(0.0)).floor() as i32
     }
 
     pub fn clear(&mut self) {
         self.buffs.clear();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adding_a_buff_again_extends_it() {
+        let mut buffs = BuffContainer::new();
+        buffs.add_buff(BuffType::Overclock, 1);
+        buffs.add_buff(BuffType::Overclock, 2);
+        assert_eq!(buffs.has_buff(BuffType::Overclock), Some(3));
+        assert_eq!(buffs.has_buff(BuffType::MalwareInjected), None);
+    }
+
+    #[test]
+    fn buffs_wear_off() {
+        let mut buffs = BuffContainer::new();
+        buffs.add_buff(BuffType::FirewallFortified, 2);
+        buffs.add_buff(BuffType::MalwareInjected, 1);
+        buffs.do_turn();
+        assert_eq!(buffs.has_buff(BuffType::FirewallFortified), Some(1));
+        assert_eq!(buffs.has_buff(BuffType::MalwareInjected), None);
+        buffs.do_turn();
+        assert_eq!(buffs.has_buff(BuffType::FirewallFortified), None);
+    }
+
+    #[test]
+    fn removing_a_buff_purges_it() {
+        let mut buffs = BuffContainer::new();
+        buffs.add_buff(BuffType::MalwareInjected, 3);
+        assert!(buffs.remove_buff(BuffType::MalwareInjected));
+        assert_eq!(buffs.has_buff(BuffType::MalwareInjected), None);
+        assert!(!buffs.remove_buff(BuffType::MalwareInjected));
+    }
+
+    #[test]
+    fn overclock_adds_bonus_damage_and_malware_takes_it_away() {
+        let mut buffs = BuffContainer::new();
+        assert_eq!(buffs.get_buff_dmg(10), 0);
+        buffs.add_buff(BuffType::Overclock, 1);
+        assert_eq!(buffs.get_buff_dmg(10), 11);
+        buffs.clear();
+        buffs.add_buff(BuffType::MalwareInjected, 1);
+        assert_eq!(buffs.get_buff_dmg(10), -2);
+    }
+
+    #[test]
+    fn malware_makes_abilities_cost_more_ram() {
+        let mut buffs = BuffContainer::new();
+        assert_eq!(buffs.get_ability_ram(10), 10);
+        buffs.add_buff(BuffType::MalwareInjected, 1);
+        assert_eq!(buffs.get_ability_ram(10), 12);
+        buffs.add_buff(BuffType::FirewallFortified, 1);
+        buffs.add_buff(BuffType::Overclock, 1);
+        assert_eq!(buffs.get_ability_ram(10), 12);
+    }
+
+    #[test]
+    fn a_fortified_firewall_absorbs_half_the_damage() {
+        let mut buffs = BuffContainer::new();
+        assert_eq!(buffs.get_dmg_taken(9), 9);
+        buffs.add_buff(BuffType::FirewallFortified, 1);
+        assert_eq!(buffs.get_dmg_taken(9), 4);
+        buffs.add_buff(BuffType::MalwareInjected, 1);
+        buffs.add_buff(BuffType::Overclock, 1);
+        assert_eq!(buffs.get_dmg_taken(9), 4);
+        buffs.do_turn();
+        assert_eq!(buffs.get_dmg_taken(9), 9);
+    }
+}
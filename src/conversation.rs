@@ -1,63 +1,228 @@
-enum ConversationLine {
-    Text(String),
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::buffs::BuffType;
+use crate::items::ItemType;
+use crate::pieces::SkillType;
+use crate::player::{Player, PlayerFlag};
+use crate::quests::QuestID;
+
+pub type NodeId = String;
+
+/// Something about the player a branch or a response depends on
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Condition {
+    HasFlag(PlayerFlag),
+    LacksFlag(PlayerFlag),
+    SkillAtLeast(SkillType, i32),
+    CreditsAtLeast(i32),
+}
+
+impl Condition {
+    pub fn met(&self, player: &Player) -> bool {
+        match self {
+            Condition::HasFlag(flag) => player.has_flag(flag),
+            Condition::LacksFlag(flag) => !player.has_flag(flag),
+            Condition::SkillAtLeast(skill, level) => player.skills.get(skill) >= *level,
+            Condition::CreditsAtLeast(amount) => player.credits >= *amount,
+        }
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Condition::HasFlag(flag) => write!(f, "needs {:?}", flag),
+            Condition::LacksFlag(flag) => write!(f, "not after {:?}", flag),
+            Condition::SkillAtLeast(skill, level) => write!(f, "{} {}", skill, level),
+            Condition::CreditsAtLeast(amount) => write!(f, "{} credits", amount),
+        }
+    }
+}
+
+/// What reaching a node does to the game, applied in order as soon as the node is shown
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum DialogueEffect {
+    SetFlag(PlayerFlag),
+    ClearFlag(PlayerFlag),
+    StartQuest(QuestID),
+    AdvanceQuest(QuestID),
+    GiveCredits(i32),
+    /// the player pays, never more than they have
+    TakeCredits(i32),
+    GiveXp(i32),
+    GiveItem(ItemType),
+    ApplyBuff(BuffType, u32),
+    /// ends the conversation in the shop
+    OpenShop,
+    /// ends the conversation in a fight with a contact from the current network
+    StartCombat,
+}
+
+fn all_met(conditions: &[Condition], player: &Player) -> bool {
+    conditions.iter().all(|condition| condition.met(player))
 }
 
-impl ConversationLine {
-    fn from_str(string: &str) -> Self {
-        ConversationLine::Text(string.to_string())
+/// One thing the player can say back
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Response {
+    pub text: String,
+    /// `None` ends the conversation
+    pub next: Option<NodeId>,
+    #[serde(default)]
+    pub conditions: Vec<Condition>,
+}
+
+impl Response {
+    pub fn available(&self, player: &Player) -> bool {
+        all_met(&self.conditions, player)
     }
 }
 
+/// A jump that is only taken if all of its conditions hold
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Branch {
+    pub conditions: Vec<Condition>,
+    pub next: NodeId,
+}
+
+/// Where a node goes once it's been read
+#[derive(Clone, Serialize, Deserialize)]
+pub enum Next {
+    Goto(NodeId),
+    /// the player picks a response
+    Choice(Vec<Response>),
+    /// the first branch whose conditions hold, `otherwise` if none do
+    Branch {
+        branches: Vec<Branch>,
+        otherwise: NodeId,
+    },
+    End,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DialogueNode {
+    /// `None` for narration
+    pub speaker: Option<String>,
+    pub text: String,
+    pub next: Next,
+    #[serde(default)]
+    pub effects: Vec<DialogueEffect>,
+}
+
+impl fmt::Display for DialogueNode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.speaker {
+            Some(speaker) => write!(f, "{}: {}", speaker, self.text),
+            None => write!(f, "{}", self.text),
+        }
+    }
+}
+
+/// A dialogue tree and how far through it the player is
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Conversation {
-    lines: Vec<ConversationLine>,
-    line_index: i32,
+    /// the id of the conversation this is a run through
+    pub id: String,
+    nodes: BTreeMap<NodeId, DialogueNode>,
+    current: NodeId,
+    /// every line read so far, responses included
+    history: Vec<String>,
+    /// the player picked a response that ends it
+    finished: bool,
 }
 
 impl Conversation {
-    pub fn test() -> Self {
-        let mut lines = vec![
-            ConversationLine::from_str("Hello"),
-            ConversationLine::from_str("World"),
-        ];
+    pub fn new(id: String, start: NodeId, nodes: BTreeMap<NodeId, DialogueNode>) -> Self {
         Self {
-            lines: lines,
-            line_index: 0,
+            id,
+            nodes,
+            current: start,
+            history: vec![],
+            finished: false,
         }
     }
 
+    pub fn current_node(&self) -> &DialogueNode {
+        self.nodes
+            .get(&self.current)
+            .expect("conversation points at a node it has")
+    }
+
     pub fn show_lines_before(&self) -> String {
         // display every line before the current one
-        let mut total_string = "".to_owned();
-        for index in 0..self.line_index {
-            match self.lines.get(index as usize) {
-                Some(convo_line) => match convo_line {
-                    ConversationLine::Text(txt) => {
-                        if index > 0 {
-                            total_string.push_str("\n");
-                        }
-                        total_string.push_str(&txt);
-                    }
-                },
-                None => {}
-            }
-        }
-        return total_string;
+        let before = if self.finished {
+            &self.history[..self.history.len() - 1]
+        } else {
+            &self.history[..]
+        };
+        before.join("\n")
     }
 
     pub fn show_line(&self) -> String {
-        match self.lines.get(self.line_index as usize) {
-            Some(convo_line) => match convo_line {
-                ConversationLine::Text(txt) => return txt.to_owned(),
-            },
-            None => return "".to_owned(),
+        match self.history.last() {
+            // the response that ended it is the last thing said
+            Some(line) if self.finished => line.clone(),
+            _ => self.current_node().to_string(),
         }
     }
 
-    pub fn next_line(&mut self) {
-        self.line_index += 1;
+    /// effects of the node the conversation just moved to
+    pub fn node_effects(&self) -> &[DialogueEffect] {
+        if self.finished {
+            &[]
+        } else {
+            &self.current_node().effects
+        }
+    }
+
+    /// every response on the current node, including ones the player can't pick yet
+    pub fn responses(&self) -> &[Response] {
+        match &self.current_node().next {
+            Next::Choice(responses) if !self.finished => responses,
+            _ => &[],
+        }
+    }
+
+    /// move past a node that doesn't ask for a choice, false if there's nowhere to go
+    pub fn next_line(&mut self, player: &Player) -> bool {
+        let next = match &self.current_node().next {
+            Next::Goto(next) => next.clone(),
+            Next::Branch {
+                branches,
+                otherwise,
+            } => branches
+                .iter()
+                .find(|branch| all_met(&branch.conditions, player))
+                .map(|branch| branch.next.clone())
+                .unwrap_or_else(|| otherwise.clone()),
+            Next::Choice(_) | Next::End => return false,
+        };
+        self.history.push(self.show_line());
+        self.current = next;
+        true
+    }
+
+    /// pick a response by its index in `responses`, false if it isn't available
+    pub fn choose(&mut self, index: usize, player: &Player) -> bool {
+        let Some(response) = self.responses().get(index).cloned() else {
+            return false;
+        };
+        if !response.available(player) {
+            return false;
+        }
+        self.history.push(self.show_line());
+        self.history.push(format!("> {}", response.text));
+        match response.next {
+            Some(next) => self.current = next,
+            None => self.finished = true,
+        }
+        true
     }
 
+    /// nothing left to read or pick, only leaving
     pub fn done(&self) -> bool {
-        self.line_index + 1 >= self.lines.len() as i32
+        self.finished || matches!(self.current_node().next, Next::End)
     }
 }
@@ -0,0 +1,400 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::conversation::{Conversation, DialogueEffect, DialogueNode, Next, NodeId};
+use crate::utils::content_path;
+
+/// where the writers keep their conversations, one per `.ron` file
+pub const DIALOGUE_DIR: &str = "content/dialogue";
+
+/// the dialogue folder the game was shipped or built with
+pub fn dialogue_dir() -> PathBuf {
+    content_path(DIALOGUE_DIR)
+}
+
+/// One conversation as written in a content file
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ConversationDef {
+    pub id: String,
+    pub start: NodeId,
+    pub nodes: BTreeMap<NodeId, DialogueNode>,
+}
+
+impl ConversationDef {
+    /// a fresh run through this conversation
+    pub fn start(&self) -> Conversation {
+        Conversation::new(self.id.clone(), self.start.clone(), self.nodes.clone())
+    }
+
+    /// every node this conversation can jump to, in the order they're written
+    fn jumps(node: &DialogueNode) -> Vec<&NodeId> {
+        match &node.next {
+            Next::Goto(next) => vec![next],
+            Next::Choice(responses) => responses
+                .iter()
+                .filter_map(|response| response.next.as_ref())
+                .collect(),
+            Next::Branch {
+                branches,
+                otherwise,
+            } => branches
+                .iter()
+                .map(|branch| &branch.next)
+                .chain(std::iter::once(otherwise))
+                .collect(),
+            Next::End => vec![],
+        }
+    }
+
+    /// everything wrong with this conversation, empty if it's good to go
+    pub fn validate(&self) -> Vec<DialogueProblem> {
+        let mut problems = vec![];
+        if !self.nodes.contains_key(&self.start) {
+            problems.push(DialogueProblem::MissingStart(self.start.clone()));
+        }
+        for (id, node) in self.nodes.iter() {
+            if let Next::Choice(responses) = &node.next {
+                if responses.is_empty() {
+                    problems.push(DialogueProblem::EmptyChoice(id.clone()));
+                } else if responses
+                    .iter()
+                    .all(|response| !response.conditions.is_empty())
+                {
+                    problems.push(DialogueProblem::NoFallback(id.clone()));
+                }
+            }
+            let negative_credits = node.effects.iter().any(|effect| {
+                matches!(
+                    effect,
+                    DialogueEffect::GiveCredits(amount) | DialogueEffect::TakeCredits(amount)
+                        if *amount < 0
+                )
+            });
+            if negative_credits {
+                problems.push(DialogueProblem::NegativeCredits(id.clone()));
+            }
+            for next in Self::jumps(node) {
+                if !self.nodes.contains_key(next) {
+                    problems.push(DialogueProblem::DanglingJump {
+                        from: id.clone(),
+                        to: next.clone(),
+                    });
+                }
+            }
+        }
+        // walk everything reachable from the start
+        let mut reached = BTreeSet::new();
+        let mut to_visit = vec![&self.start];
+        while let Some(id) = to_visit.pop() {
+            if !reached.insert(id) {
+                continue;
+            }
+            if let Some(node) = self.nodes.get(id) {
+                to_visit.extend(Self::jumps(node));
+            }
+        }
+        for id in self.nodes.keys() {
+            if !reached.contains(id) {
+                problems.push(DialogueProblem::Unreachable(id.clone()));
+            }
+        }
+        problems
+    }
+}
+
+/// Something a writer needs to fix in a conversation
+#[derive(Debug)]
+pub enum DialogueProblem {
+    MissingStart(NodeId),
+    DanglingJump {
+        from: NodeId,
+        to: NodeId,
+    },
+    Unreachable(NodeId),
+    EmptyChoice(NodeId),
+    /// every response has conditions, so a player who meets none of them has nothing to pick
+    NoFallback(NodeId),
+    /// payments go through `TakeCredits`, not a negative `GiveCredits`
+    NegativeCredits(NodeId),
+    DuplicateId(String),
+}
+
+impl fmt::Display for DialogueProblem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DialogueProblem::MissingStart(start) => {
+                write!(f, "start node '{}' doesn't exist", start)
+            }
+            DialogueProblem::DanglingJump { from, to } => {
+                write!(f, "node '{}' jumps to missing node '{}'", from, to)
+            }
+            DialogueProblem::Unreachable(id) => {
+                write!(f, "node '{}' can't be reached from the start", id)
+            }
+            DialogueProblem::EmptyChoice(id) => {
+                write!(f, "node '{}' asks for a choice but has no responses", id)
+            }
+            DialogueProblem::NoFallback(id) => {
+                write!(
+                    f,
+                    "node '{}' has no response without conditions, the player could get stuck",
+                    id
+                )
+            }
+            DialogueProblem::NegativeCredits(id) => {
+                write!(
+                    f,
+                    "node '{}' gives or takes a negative amount of credits",
+                    id
+                )
+            }
+            DialogueProblem::DuplicateId(id) => {
+                write!(
+                    f,
+                    "conversation id '{}' is already used by another file",
+                    id
+                )
+            }
+        }
+    }
+}
+
+/// Why a content file was skipped
+#[derive(Debug)]
+pub enum DialogueErrorKind {
+    Io(io::Error),
+    /// also covers unknown flags, skills or quests, which don't parse
+    Parse(ron::error::SpannedError),
+    Invalid(DialogueProblem),
+}
+
+#[derive(Debug)]
+pub struct DialogueError {
+    pub file: PathBuf,
+    pub kind: DialogueErrorKind,
+}
+
+impl fmt::Display for DialogueError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: ", self.file.display())?;
+        match &self.kind {
+            DialogueErrorKind::Io(err) => write!(f, "could not read file: {}", err),
+            DialogueErrorKind::Parse(err) => write!(f, "{}", err),
+            DialogueErrorKind::Invalid(problem) => write!(f, "{}", problem),
+        }
+    }
+}
+
+/// Every conversation that loaded cleanly, by id
+#[derive(Default)]
+pub struct DialogueLibrary {
+    conversations: BTreeMap<String, ConversationDef>,
+}
+
+impl DialogueLibrary {
+    /// load every `.ron` file in `dir`; broken files are skipped and reported, not fatal
+    pub fn load_dir(dir: &Path) -> (Self, Vec<DialogueError>) {
+        let mut library = Self::default();
+        let mut errors = vec![];
+        let mut files = match fs::read_dir(dir) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == "ron"))
+                .collect::<Vec<_>>(),
+            Err(err) => {
+                errors.push(DialogueError {
+                    file: dir.to_path_buf(),
+                    kind: DialogueErrorKind::Io(err),
+                });
+                return (library, errors);
+            }
+        };
+        // same order on every platform
+        files.sort();
+        for file in files {
+            if let Err(kind) = library.load_file(&file) {
+                errors.extend(kind.into_iter().map(|kind| DialogueError {
+                    file: file.clone(),
+                    kind,
+                }));
+            }
+        }
+        (library, errors)
+    }
+
+    fn load_file(&mut self, file: &Path) -> Result<(), Vec<DialogueErrorKind>> {
+        let text = fs::read_to_string(file).map_err(|err| vec![DialogueErrorKind::Io(err)])?;
+        let def: ConversationDef =
+            ron::from_str(&text).map_err(|err| vec![DialogueErrorKind::Parse(err)])?;
+        self.add(def).map_err(|problems| {
+            problems
+                .into_iter()
+                .map(DialogueErrorKind::Invalid)
+                .collect()
+        })
+    }
+
+    /// add `def` if it's valid and its id isn't taken yet
+    pub fn add(&mut self, def: ConversationDef) -> Result<(), Vec<DialogueProblem>> {
+        let mut problems = def.validate();
+        if self.conversations.contains_key(&def.id) {
+            problems.push(DialogueProblem::DuplicateId(def.id.clone()));
+        }
+        if !problems.is_empty() {
+            return Err(problems);
+        }
+        self.conversations.insert(def.id.clone(), def);
+        Ok(())
+    }
+
+    pub fn get(&self, id: &str) -> Option<&ConversationDef> {
+        self.conversations.get(id)
+    }
+
+    /// every conversation, by id
+    pub fn iter(&self) -> impl Iterator<Item = &ConversationDef> {
+        self.conversations.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.conversations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.conversations.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn conversation(id: &str, start: &str, nodes: &str) -> ConversationDef {
+        ron::from_str(&format!(
+            "#![enable(implicit_some)] (id: {:?}, start: {:?}, nodes: {{ {} }})",
+            id, start, nodes
+        ))
+        .expect("test conversation parses")
+    }
+
+    fn problems(def: &ConversationDef) -> Vec<String> {
+        def.validate()
+            .iter()
+            .map(|problem| problem.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn a_well_formed_conversation_has_no_problems() {
+        let def = conversation(
+            "ok",
+            "start",
+            r#""start": (text: "Hi.", next: Choice([
+                (text: "Pay.", next: "paid", conditions: [CreditsAtLeast(10)]),
+                (text: "Leave."),
+            ])),
+            "paid": (text: "Thanks.", next: End),"#,
+        );
+        assert!(problems(&def).is_empty());
+    }
+
+    #[test]
+    fn a_missing_start_is_reported() {
+        let def = conversation("convo", "nowhere", r#""start": (text: "Hi.", next: End),"#);
+        assert!(matches!(
+            def.validate().as_slice(),
+            [DialogueProblem::MissingStart(start), DialogueProblem::Unreachable(_)]
+                if start == "nowhere"
+        ));
+    }
+
+    #[test]
+    fn a_jump_to_a_missing_node_is_reported() {
+        let def = conversation(
+            "convo",
+            "start",
+            r#""start": (text: "Hi.", next: Goto("gone")),"#,
+        );
+        assert!(matches!(
+            def.validate().as_slice(),
+            [DialogueProblem::DanglingJump { from, to }] if from == "start" && to == "gone"
+        ));
+    }
+
+    #[test]
+    fn an_unreachable_node_is_reported() {
+        let def = conversation(
+            "convo",
+            "start",
+            r#""start": (text: "Hi.", next: End),
+            "orphan": (text: "Anyone?", next: End),"#,
+        );
+        assert!(matches!(
+            def.validate().as_slice(),
+            [DialogueProblem::Unreachable(id)] if id == "orphan"
+        ));
+    }
+
+    #[test]
+    fn a_choice_without_responses_is_reported() {
+        let def = conversation(
+            "convo",
+            "start",
+            r#""start": (text: "Hi.", next: Choice([])),"#,
+        );
+        assert!(matches!(
+            def.validate().as_slice(),
+            [DialogueProblem::EmptyChoice(id)] if id == "start"
+        ));
+    }
+
+    #[test]
+    fn a_choice_where_every_response_has_conditions_is_reported() {
+        let def = conversation(
+            "convo",
+            "start",
+            r#""start": (text: "Hi.", next: Choice([
+                (text: "Pay.", conditions: [CreditsAtLeast(10)]),
+                (text: "Flash your rep.", conditions: [SkillAtLeast(Hacking, 3)]),
+            ])),"#,
+        );
+        assert!(matches!(
+            def.validate().as_slice(),
+            [DialogueProblem::NoFallback(id)] if id == "start"
+        ));
+    }
+
+    #[test]
+    fn a_second_conversation_with_the_same_id_is_rejected() {
+        let nodes = r#""start": (text: "Hi.", next: End),"#;
+        let mut library = DialogueLibrary::default();
+        assert!(library.add(conversation("twin", "start", nodes)).is_ok());
+        let problems = library
+            .add(conversation("twin", "start", nodes))
+            .unwrap_err();
+        assert!(matches!(
+            problems.as_slice(),
+            [DialogueProblem::DuplicateId(id)] if id == "twin"
+        ));
+        assert_eq!(library.len(), 1);
+    }
+
+    #[test]
+    fn negative_credit_amounts_are_reported() {
+        let def = conversation(
+            "convo",
+            "start",
+            r#""start": (text: "Hi.", next: Goto("paid"), effects: [GiveCredits(-50)]),
+            "paid": (text: "Thanks.", next: End, effects: [TakeCredits(-5)]),"#,
+        );
+        assert!(matches!(
+            def.validate().as_slice(),
+            [DialogueProblem::NegativeCredits(paid), DialogueProblem::NegativeCredits(start)]
+                if paid == "paid" && start == "start"
+        ));
+    }
+}
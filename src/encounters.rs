@@ -0,0 +1,66 @@
+use crate::conversation::Condition;
+use crate::engine::{Tasks, SIPR_INTEL_LEVEL};
+use crate::pieces::Networks;
+use crate::player::PlayerFlag;
+
+/// Someone the player can run into while doing a task
+pub struct EncounterDef {
+    /// shown before the conversation starts
+    pub name: &'static str,
+    /// id of the conversation in the dialogue content
+    pub conversation: &'static str,
+    pub net: Networks,
+    /// tasks that can lead to this encounter
+    pub tasks: &'static [Tasks],
+    pub min_intel_level: i32,
+    pub conditions: &'static [Condition],
+    /// chance per task, once everything else allows it
+    pub chance: f32,
+    /// only ever happens once per run
+    pub once: bool,
+}
+
+/// NPCs who might turn up during a task, checked top to bottom until one does
+pub const ENCOUNTERS: &[EncounterDef] = &[
+    EncounterDef {
+        name: "A fixer",
+        conversation: "fixer_intro",
+        net: Networks::Internet,
+        tasks: &[Tasks::Search],
+        min_intel_level: 0,
+        // keeps turning up until the player is good enough to take the contract
+        conditions: &[Condition::LacksFlag(PlayerFlag::HiredByFixer)],
+        chance: 0.08,
+        once: false,
+    },
+    EncounterDef {
+        name: "An informant",
+        conversation: "informant",
+        net: Networks::Internet,
+        tasks: &[Tasks::Search, Tasks::Datamine],
+        min_intel_level: 1,
+        conditions: &[],
+        chance: 0.05,
+        once: false,
+    },
+    EncounterDef {
+        name: "A rival runner",
+        conversation: "rival_runner",
+        net: Networks::Internet,
+        tasks: &[Tasks::Datamine],
+        min_intel_level: 2,
+        conditions: &[Condition::LacksFlag(PlayerFlag::UnlockedNetworkSIPR)],
+        chance: 0.07,
+        once: false,
+    },
+    EncounterDef {
+        name: "A government insider",
+        conversation: "sipr_insider",
+        net: Networks::Internet,
+        tasks: &[Tasks::Search],
+        min_intel_level: SIPR_INTEL_LEVEL,
+        conditions: &[Condition::LacksFlag(PlayerFlag::UnlockedNetworkSIPR)],
+        chance: 0.1,
+        once: false,
+    },
+];
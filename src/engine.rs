@@ -0,0 +1,2259 @@
+use rand::{thread_rng, Rng};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::abilities::{ability_def, AbilityEffect, AbilityType, Targeting};
+use crate::behaviour::{ContactAbility, Intent, Situation, MAX_CONTACTS};
+use crate::buffs::BuffType;
+use crate::conversation::{Conversation, DialogueEffect};
+use crate::dialogue::DialogueLibrary;
+use crate::encounters::ENCOUNTERS;
+use crate::events::{EventListener, GameEvent};
+use crate::items::{ItemType, EXPLOIT_DAMAGE, RAM_PATCH_AMOUNT, REPAIR_KIT_AMOUNT};
+use crate::loot::{drop_quality, loot_table, Loot, LootDrop};
+use crate::pieces::{Contact, Networks, SkillType, Skills, BASE_SKILL_POINTS};
+use crate::player::{
+    intel_level, NetStats, Perk, Player, PlayerFlag, PlayerUpgradeType, LEVELS_PER_PERK,
+    SKILL_POINTS_PER_LEVEL,
+};
+use crate::quests::{quest_def, quest_name, reward_summary, QuestID, QuestReward};
+use crate::records::RunRecord;
+use crate::replay::{LoggedAction, Replay, REPLAY_VERSION};
+use crate::upgrades::{upgrade_def, UpgradeEffect, CATALOG};
+use crate::utils::{roll_encounter, seeded_rng, GameRng};
+
+/// RAM the Overclocker perk takes off overclocking
+pub const OVERCLOCKER_DISCOUNT: i32 = 4;
+/// turns an injected player stays infected
+pub const MALWARE_DURATION: u32 = 3;
+/// turns a fortified contact takes half damage
+pub const FORTIFY_DURATION: u32 = 2;
+/// Internet intel level before the insider gets in touch about SIPRnet
+pub const SIPR_INTEL_LEVEL: i32 = 2;
+/// task success chance gained per intel level on the current network
+pub const INTEL_SUCCESS_BONUS: f32 = 0.03;
+/// SIPRnet credentials in the shop, once the insider has vouched for the player
+pub const SIPR_CREDENTIALS_COST: i32 = 500;
+/// credits per skill point refunded by a respec
+pub const RESPEC_COST_PER_POINT: i32 = 25;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Tasks {
+    Search,
+    Datamine,
+    Recover,
+    Exfiltrate,
+}
+
+impl Tasks {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Tasks::Search => "Search around",
+            Tasks::Datamine => "Datamine",
+            Tasks::Recover => "Recover",
+            Tasks::Exfiltrate => "Exfiltrate",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match *self {
+            Tasks::Search => "+ Credits, ???",
+            Tasks::Datamine => "High risk, ++ Credits",
+            Tasks::Recover => "Low risk, ++ RAM",
+            Tasks::Exfiltrate => "Very high risk, +++ Credits, data fragments",
+        }
+    }
+
+    /// intel level the current network needs before the task can be done
+    pub fn min_intel_level(&self) -> i32 {
+        match *self {
+            Tasks::Search | Tasks::Datamine | Tasks::Recover => 0,
+            Tasks::Exfiltrate => 3,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum InteractionType {
+    BasicShop,
+}
+
+/// An activity describes what the player is currently doing
+#[derive(Serialize, Deserialize)]
+pub enum Activity {
+    FreeRoam,
+    Combat(Vec<Contact>),
+    Interacting(InteractionType),
+    Conversing(Conversation),
+    /// HP hit zero, waiting to respawn, or for good in ironman
+    Flatlined {
+        killed_by: Option<String>,
+    },
+}
+
+/// How harsh a flatline is, picked when a run starts
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GameSettings {
+    /// share of credits lost on a flatline, 0.0 to 1.0
+    pub credit_penalty: f32,
+    /// wipe intel on the network the player flatlined on
+    pub reset_intel: bool,
+    pub clear_buffs: bool,
+    /// a flatline ends the run for good
+    pub ironman: bool,
+    /// quest rewards wait in the quest log until claimed
+    pub manual_quest_rewards: bool,
+}
+
+impl Default for GameSettings {
+    fn default() -> Self {
+        Self {
+            credit_penalty: 0.25,
+            reset_intel: true,
+            clear_buffs: true,
+            ironman: false,
+            manual_quest_rewards: false,
+        }
+    }
+}
+
+/// Everything the player can do, in any activity
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Action {
+    DoTask(Tasks),
+    ChangeNetwork(Networks),
+    /// commit points on top of the current skills, committed points only come back with a respec
+    AllocateSkills(Skills),
+    Respec,
+    ChoosePerk(Perk),
+    EnterShop,
+    LeaveShop,
+    BuyUpgrade(PlayerUpgradeType),
+    BuyItem(ItemType),
+    BuyNetworkAccess(Networks),
+    /// consumables work in free roam and in combat, some only in combat
+    UseItem(ItemType),
+    /// use an ability from the combat bar; `target` is a contact's index in the fight,
+    /// ignored by abilities that don't pick one
+    UseAbility {
+        ability: AbilityType,
+        target: usize,
+    },
+    Escape,
+    ContinueConversation,
+    /// index into the current node's responses, locked ones included
+    ChooseResponse(usize),
+    EndConversation,
+    Respawn,
+    ClaimQuestReward(QuestID),
+}
+
+/// Something that happened as a result of an action
+#[derive(Clone, Debug, PartialEq)]
+pub enum Outcome {
+    CreditsFound {
+        task: Tasks,
+        chance: f32,
+        amount: i32,
+    },
+    NothingFound {
+        chance: f32,
+        ram: i32,
+    },
+    AlreadyRecovered,
+    RamRecovered(i32),
+    HpRecovered(i32),
+    ShopDiscovered,
+    ShopEntered(Networks),
+    ShopLeft,
+    SkillsAllocated(Skills),
+    Respecced {
+        refunded: i32,
+        cost: i32,
+    },
+    UpgradeBought(PlayerUpgradeType),
+    UpgradeUnlocked(PlayerUpgradeType),
+    ItemBought(ItemType),
+    ItemUsed(ItemType),
+    LootDropped {
+        name: String,
+        loot: Loot,
+    },
+    NetworkChanged(Networks),
+    CombatStarted {
+        chance: f32,
+        contact: String,
+    },
+    ContactJoined(String),
+    DataExfiltrated(i32),
+    DamageDealt {
+        target: String,
+        amount: i32,
+        bonus: i32,
+    },
+    DamageTaken {
+        source: String,
+        amount: i32,
+    },
+    MalwareInjected {
+        source: String,
+    },
+    ContactFortified(String),
+    RamDrained {
+        source: String,
+        amount: i32,
+    },
+    SubprocessSpawned {
+        source: String,
+        spawned: String,
+    },
+    ContactRepaired {
+        name: String,
+        amount: i32,
+    },
+    ContactDefeated {
+        name: String,
+        xp: i32,
+    },
+    AbilityUsed(AbilityType),
+    AbilityLearned(AbilityType),
+    BuffGained {
+        buff: BuffType,
+        turns: u32,
+    },
+    BuffPurged(BuffType),
+    Escaped,
+    QuestCompleted {
+        quest: QuestID,
+        rewards: Vec<QuestReward>,
+        /// false if the rewards are waiting in the quest log
+        claimed: bool,
+    },
+    QuestRewardClaimed {
+        quest: QuestID,
+        rewards: Vec<QuestReward>,
+    },
+    ObjectiveCompleted {
+        quest: QuestID,
+        next: String,
+    },
+    XpGained(i32),
+    LevelUp {
+        level: i32,
+        skill_points: i32,
+        perk_available: bool,
+    },
+    PerkChosen(Perk),
+    ConversationEnded,
+    CreditsReceived(i32),
+    CreditsPaid(i32),
+    ItemReceived(ItemType),
+    BuffApplied(BuffType),
+    QuestStarted(QuestID),
+    NetworkUnlocked(Networks),
+    ConversationTurnedHostile {
+        contact: String,
+    },
+    NpcEncountered(String),
+    Flatlined {
+        killed_by: Option<String>,
+        credits_lost: i32,
+        intel_lost: f32,
+    },
+    /// an ironman run is over
+    RunEnded,
+    Respawned,
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Outcome::CreditsFound {
+                task: Tasks::Search,
+                chance,
+                amount,
+            } => write!(
+                f,
+                "({:.1}) You found some particularly interesting data worth {} credits",
+                chance, amount
+            ),
+            Outcome::CreditsFound { chance, amount, .. } => write!(
+                f,
+                "({:.1}) You found some interesting data worth {} credits",
+                chance, amount
+            ),
+            Outcome::NothingFound { chance, ram } => write!(
+                f,
+                "({:.1}) You don't find anything new, but regenerate {} RAM",
+                chance, ram
+            ),
+            Outcome::AlreadyRecovered => write!(f, "You are already at maximum HP and RAM"),
+            Outcome::RamRecovered(amount) => write!(f, "You recover {} RAM", amount),
+            Outcome::HpRecovered(amount) => write!(f, "You recover {} HP", amount),
+            Outcome::ShopDiscovered => write!(
+                f,
+                "You stumble across some sort of virtual server for secure transations."
+            ),
+            Outcome::ShopEntered(net) => write!(
+                f,
+                "You see what's available for purchase on {}.",
+                match net {
+                    Networks::Internet => "the internet",
+                    Networks::SIPRnet => "SIPRnet",
+                }
+            ),
+            Outcome::ShopLeft => write!(f, "You leave the shop."),
+            Outcome::SkillsAllocated(skills) => write!(
+                f,
+                "Skills committed: Hacking {}, Security {}.",
+                skills.hacking, skills.security
+            ),
+            Outcome::Respecced { refunded, cost } => write!(
+                f,
+                "You pay {} credits to wipe your skills, {} points are free to spend again.",
+                cost, refunded
+            ),
+            Outcome::UpgradeBought(upgrade) => write!(f, "You bought {}!", upgrade.name()),
+            Outcome::UpgradeUnlocked(upgrade) => {
+                write!(f, "The shop now stocks {}.", upgrade.name())
+            }
+            Outcome::ItemBought(item) => write!(f, "You bought a {}.", item.name()),
+            Outcome::ItemUsed(item) => write!(f, "You run a {}.", item.name()),
+            Outcome::LootDropped { name, loot } => write!(f, "{} dropped {}.", name, loot),
+            Outcome::NetworkChanged(net) => write!(f, "You connect to {}.", net),
+            Outcome::CombatStarted { chance, contact } => write!(
+                f,
+                "({:.1}) You run into a nasty piece of malware - {}",
+                chance, contact
+            ),
+            Outcome::ContactJoined(contact) => write!(f, "{} joins the fight", contact),
+            Outcome::DataExfiltrated(fragments) => {
+                write!(f, "You slip out with {} data fragments", fragments)
+            }
+            Outcome::DamageDealt {
+                target,
+                amount,
+                bonus,
+            } => {
+                if *bonus > 0 {
+                    write!(f, "You deal {} + {} damage to {}.", amount, bonus, target)
+                } else if *bonus < 0 {
+                    // malware eats into the hit
+                    write!(f, "You deal {} - {} damage to {}.", amount, -bonus, target)
+                } else {
+                    write!(f, "You deal {} damage to {}.", amount, target)
+                }
+            }
+            Outcome::DamageTaken { source, amount } => {
+                write!(f, "You take {} damage from {}.", amount, source)
+            }
+            Outcome::MalwareInjected { source } => {
+                write!(f, "{} injects malware into your systems.", source)
+            }
+            Outcome::ContactFortified(name) => write!(f, "{} fortifies its firewall.", name),
+            Outcome::RamDrained { source, amount } => {
+                write!(f, "{} drains {} of your RAM.", source, amount)
+            }
+            Outcome::SubprocessSpawned { source, spawned } => {
+                write!(f, "{} spawns a sub-process: {}.", source, spawned)
+            }
+            Outcome::ContactRepaired { name, amount } => {
+                write!(f, "{} patches itself up, restoring {} HP.", name, amount)
+            }
+            Outcome::ContactDefeated { name, xp: 0 } => write!(f, "{} goes dark.", name),
+            Outcome::ContactDefeated { name, xp } => {
+                write!(f, "{} goes dark. (+{} XP)", name, xp)
+            }
+            Outcome::AbilityUsed(ability) => write!(f, "You run {}.", ability.name()),
+            Outcome::AbilityLearned(ability) => {
+                write!(f, "{} is now on your combat bar.", ability.name())
+            }
+            Outcome::BuffGained { buff, turns } => {
+                write!(f, "{} is active for {} turn(s).", buff.name(), turns)
+            }
+            Outcome::BuffPurged(buff) => write!(f, "You scrub {} from your systems.", buff.name()),
+            Outcome::Escaped => write!(f, "You escape from combat."),
+            Outcome::QuestCompleted {
+                quest,
+                rewards,
+                claimed,
+            } => {
+                write!(f, "You finish a quest: {}!", quest_name(quest))?;
+                match (rewards.is_empty(), claimed) {
+                    (true, _) => Ok(()),
+                    (false, true) => write!(f, " Rewards: {}.", reward_summary(rewards)),
+                    (false, false) => {
+                        write!(f, " Claim {} from the quest log.", reward_summary(rewards))
+                    }
+                }
+            }
+            Outcome::QuestRewardClaimed { quest, rewards } => write!(
+                f,
+                "You claim {} for {}.",
+                reward_summary(rewards),
+                quest_name(quest)
+            ),
+            Outcome::ObjectiveCompleted { quest, next } => write!(
+                f,
+                "{}: objective complete. Next up: {}.",
+                quest_name(quest),
+                next
+            ),
+            Outcome::XpGained(amount) => write!(f, "You gained {} XP.", amount),
+            Outcome::LevelUp {
+                level,
+                skill_points,
+                perk_available,
+            } => {
+                write!(
+                    f,
+                    "You reached level {}! +{} skill points.",
+                    level, skill_points
+                )?;
+                if *perk_available {
+                    write!(f, " A new perk is available.")?;
+                }
+                Ok(())
+            }
+            Outcome::PerkChosen(perk) => {
+                write!(
+                    f,
+                    "You picked the {} perk: {}.",
+                    perk.name(),
+                    perk.description()
+                )
+            }
+            Outcome::ConversationEnded => write!(f, "The conversation is over."),
+            Outcome::CreditsReceived(amount) => write!(f, "You receive {} credits.", amount),
+            Outcome::CreditsPaid(amount) => write!(f, "You pay {} credits.", amount),
+            Outcome::ItemReceived(item) => write!(f, "You receive a {}.", item.name()),
+            Outcome::BuffApplied(buff) => write!(f, "You are affected by {}.", buff.name()),
+            Outcome::QuestStarted(quest_id) => write!(f, "New quest: {}.", quest_name(quest_id)),
+            Outcome::NetworkUnlocked(net) => write!(f, "You now have access to {}.", net),
+            Outcome::NpcEncountered(name) => {
+                write!(f, "{} pings you on a private channel.", name)
+            }
+            Outcome::ConversationTurnedHostile { contact } => {
+                write!(f, "The talk goes south - {} attacks!", contact)
+            }
+            Outcome::Flatlined {
+                killed_by,
+                credits_lost,
+                intel_lost,
+            } => {
+                match killed_by {
+                    Some(name) => write!(f, "{} flatlines you!", name)?,
+                    None => write!(f, "You flatline!")?,
+                }
+                if *credits_lost > 0 {
+                    write!(f, " You lose {} credits.", credits_lost)?;
+                }
+                if *intel_lost > 0.0 {
+                    write!(f, " Your intel on this network is wiped.")?;
+                }
+                Ok(())
+            }
+            Outcome::RunEnded => write!(f, "Your run is over. It has been recorded."),
+            Outcome::Respawned => write!(f, "You reboot from a backup and jack back in."),
+        }
+    }
+}
+
+/// Why an action could not be taken; the game state is left untouched
+#[derive(Clone, Debug, PartialEq)]
+pub enum ActionError {
+    /// the action doesn't make sense in the current activity
+    WrongActivity,
+    NotEnoughRam {
+        needed: i32,
+    },
+    NotEnoughCredits {
+        needed: i32,
+    },
+    NoSkillPoints,
+    InvalidAllocation,
+    NothingToRespec,
+    NoRewardToClaim,
+    NoSuchTarget(usize),
+    UnknownAbility(AbilityType),
+    AbilityCoolingDown {
+        turns: u32,
+    },
+    NothingToPurge(BuffType),
+    NoPerkPoints,
+    PerkOwned(Perk),
+    NetworkLocked(Networks),
+    UpgradeUnavailable,
+    UpgradeMaxed,
+    SkillCapped(SkillType),
+    NoItem(ItemType),
+    /// ironman runs can't respawn
+    RunOver,
+    NotForSale,
+    ResponseUnavailable,
+    IntelTooLow {
+        needed: i32,
+    },
+}
+
+impl fmt::Display for ActionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ActionError::WrongActivity => write!(f, "You can't do that right now."),
+            ActionError::NotEnoughRam { needed } => {
+                write!(f, "You need {} RAM to use that ability.", needed)
+            }
+            ActionError::NotEnoughCredits { needed } => {
+                write!(f, "You need {} credits to buy that.", needed)
+            }
+            ActionError::NoSkillPoints => write!(f, "You don't have any skill points left."),
+            ActionError::InvalidAllocation => {
+                write!(
+                    f,
+                    "Committed skill points can only be taken back with a respec."
+                )
+            }
+            ActionError::NothingToRespec => write!(f, "Your skills are already at their minimum."),
+            ActionError::NoRewardToClaim => write!(f, "There's nothing to claim for that quest."),
+            ActionError::NoSuchTarget(_) => write!(f, "There's no contact there to hack."),
+            ActionError::UnknownAbility(ability) => {
+                write!(f, "You don't know how to use {}.", ability.name())
+            }
+            ActionError::AbilityCoolingDown { turns } => {
+                write!(f, "That ability is ready again in {} turn(s).", turns)
+            }
+            ActionError::NothingToPurge(buff) => {
+                write!(f, "There's no {} on your systems to purge.", buff.name())
+            }
+            ActionError::NoPerkPoints => write!(f, "You can't pick another perk yet."),
+            ActionError::PerkOwned(perk) => write!(f, "You already have {}.", perk.name()),
+            ActionError::NetworkLocked(net) => write!(f, "You don't have access to {}.", net),
+            ActionError::UpgradeUnavailable => write!(f, "That upgrade isn't for sale."),
+            ActionError::UpgradeMaxed => write!(f, "That upgrade is already maxed out."),
+            ActionError::SkillCapped(skill) => {
+                write!(f, "{} can't go any higher without an upgrade.", skill)
+            }
+            ActionError::NoItem(item) => write!(f, "You don't have a {}.", item.name()),
+            ActionError::RunOver => write!(f, "This run is over, start a new one."),
+            ActionError::NotForSale => write!(f, "That isn't for sale."),
+            ActionError::ResponseUnavailable => write!(f, "You can't say that."),
+            ActionError::IntelTooLow { needed } => write!(
+                f,
+                "You need intel level {} on this network for that.",
+                needed
+            ),
+        }
+    }
+}
+
+/// The complete, UI-independent state of a run
+#[derive(Serialize, Deserialize)]
+pub struct Game {
+    // game objects
+    pub player: Player,
+    // enums
+    pub activity: Activity,
+    pub current_net: Networks,
+    // values
+    pub turn: i32,
+    /// the seed this run started from, share it to reproduce the run
+    pub seed: u64,
+    pub settings: GameSettings,
+    /// every random roll in the game comes from here
+    rng: GameRng,
+    /// every action applied so far, for replays
+    log: Vec<LoggedAction>,
+    /// real time spent idle since the last action
+    pending_idle_seconds: f32,
+    /// outcomes of the action currently being applied
+    #[serde(skip)]
+    outcomes: Vec<Outcome>,
+    /// the contact that landed the killing blow during the current action
+    #[serde(skip)]
+    killed_by: Option<String>,
+    /// conversations from the content files, loaded by the front end
+    #[serde(skip)]
+    dialogue: DialogueLibrary,
+}
+
+impl Default for Game {
+    /// a fresh run with a random seed
+    fn default() -> Self {
+        Self::new(thread_rng().gen())
+    }
+}
+
+impl Game {
+    /// a fresh run; the same seed and the same actions always play out the same way
+    pub fn new(seed: u64) -> Self {
+        Self::with_settings(seed, GameSettings::default())
+    }
+
+    pub fn with_settings(seed: u64, settings: GameSettings) -> Self {
+        let mut rng = seeded_rng(seed);
+        Self {
+            player: Player::new(&mut rng),
+            activity: Activity::FreeRoam,
+            current_net: Networks::Internet,
+            turn: 1,
+            seed,
+            settings,
+            rng,
+            log: vec![],
+            pending_idle_seconds: 0.0,
+            outcomes: vec![],
+            killed_by: None,
+            dialogue: DialogueLibrary::default(),
+        }
+    }
+
+    /// Apply a single player action and report everything that happened because of it
+    pub fn apply(&mut self, action: Action) -> Result<Vec<Outcome>, ActionError> {
+        let turn = self.turn;
+        // idle intel is folded in as one lump per action so a replay adds up exactly the same
+        let intel_before = self.current_net_stats_mut().total_intel;
+        let idle_seconds = std::mem::take(&mut self.pending_idle_seconds);
+        self.current_net_stats_mut().total_intel += idle_seconds;
+
+        match self.resolve(action.clone()) {
+            Ok(()) => {
+                self.log.push(LoggedAction {
+                    turn,
+                    idle_seconds,
+                    action,
+                });
+                Ok(std::mem::take(&mut self.outcomes))
+            }
+            Err(err) => {
+                // rejected actions leave no trace, not even in the log
+                self.current_net_stats_mut().total_intel = intel_before;
+                self.pending_idle_seconds = idle_seconds;
+                self.outcomes.clear();
+                Err(err)
+            }
+        }
+    }
+
+    fn resolve(&mut self, action: Action) -> Result<(), ActionError> {
+        match action {
+            Action::DoTask(task) => {
+                self.require_free_roam()?;
+                self.require_intel_level(task.min_intel_level())?;
+                self.do_task(task);
+            }
+            Action::ChangeNetwork(net) => {
+                self.require_free_roam()?;
+                if !self.network_available(&net) {
+                    return Err(ActionError::NetworkLocked(net));
+                }
+                if net != self.current_net {
+                    self.current_net = net.clone();
+                    self.report(Outcome::NetworkChanged(net.clone()));
+                    self.emit(GameEvent::NetworkChanged(net));
+                }
+            }
+            Action::AllocateSkills(points) => {
+                self.require_free_roam()?;
+                if SkillType::ALL.iter().any(|skill| points.get(skill) < 0)
+                    || points.total_points() == 0
+                {
+                    return Err(ActionError::InvalidAllocation);
+                }
+                if points.total_points() > self.player.available_skill_points() {
+                    return Err(ActionError::NoSkillPoints);
+                }
+                for skill in SkillType::ALL.iter() {
+                    if self.player.skills.get(skill) + points.get(skill)
+                        > self.player.skill_cap(skill)
+                    {
+                        return Err(ActionError::SkillCapped(*skill));
+                    }
+                }
+                for skill in SkillType::ALL.iter() {
+                    *self.player.skills.get_mut(skill) += points.get(skill);
+                }
+                self.report(Outcome::SkillsAllocated(self.player.skills.clone()));
+            }
+            Action::Respec => {
+                self.require_shopping()?;
+                let refunded = self.respec_refund();
+                if refunded == 0 {
+                    return Err(ActionError::NothingToRespec);
+                }
+                let cost = refunded * RESPEC_COST_PER_POINT;
+                if self.player.credits < cost {
+                    return Err(ActionError::NotEnoughCredits { needed: cost });
+                }
+                self.spend_credits(cost);
+                for skill in SkillType::ALL.iter() {
+                    *self.player.skills.get_mut(skill) = skill.floor();
+                }
+                self.report(Outcome::Respecced { refunded, cost });
+            }
+            Action::ChoosePerk(perk) => {
+                self.require_free_roam()?;
+                if self.player.available_perk_points() <= 0 {
+                    return Err(ActionError::NoPerkPoints);
+                }
+                if self.player.has_perk(perk) {
+                    return Err(ActionError::PerkOwned(perk));
+                }
+                self.choose_perk(perk);
+            }
+            Action::EnterShop => {
+                self.require_free_roam()?;
+                if !self.player.has_flag(&PlayerFlag::DiscoveredShopBasic) {
+                    return Err(ActionError::WrongActivity);
+                }
+                self.go_shopping();
+            }
+            Action::LeaveShop => {
+                self.require_shopping()?;
+                self.activity = Activity::FreeRoam;
+                self.player.enable_flag(PlayerFlag::DiscoveredShopBasic);
+                self.report(Outcome::ShopLeft);
+            }
+            Action::BuyUpgrade(upgrade) => {
+                self.require_shopping()?;
+                self.buy_upgrade(upgrade)?;
+            }
+            Action::BuyItem(item) => {
+                self.require_shopping()?;
+                self.require_intel_level(item.min_intel_level())?;
+                let cost = item.price();
+                if self.player.credits < cost {
+                    return Err(ActionError::NotEnoughCredits { needed: cost });
+                }
+                self.spend_credits(cost);
+                self.player.inventory.add(item, 1);
+                self.report(Outcome::ItemBought(item));
+                self.emit(GameEvent::ItemBought(item));
+            }
+            Action::BuyNetworkAccess(net) => {
+                self.require_shopping()?;
+                let cost = match self.network_access_price(&net) {
+                    Some(cost) => cost,
+                    None => return Err(ActionError::NotForSale),
+                };
+                if self.player.credits < cost {
+                    return Err(ActionError::NotEnoughCredits { needed: cost });
+                }
+                self.spend_credits(cost);
+                self.unlock_network(&net);
+            }
+            Action::UseItem(item) => {
+                match self.activity {
+                    Activity::Combat(_) => {}
+                    Activity::FreeRoam if !item.combat_only() => {}
+                    _ => return Err(ActionError::WrongActivity),
+                }
+                if !self.player.inventory.take(item) {
+                    return Err(ActionError::NoItem(item));
+                }
+                self.use_item(item);
+                self.emit(GameEvent::ItemUsed(item));
+                // using an item in a fight takes the player's turn
+                if matches!(self.activity, Activity::Combat(_)) {
+                    self.contacts_act();
+                    self.clear_defeated_contacts();
+                    self.do_turn();
+                }
+            }
+            Action::UseAbility { ability, target } => {
+                self.require_combat()?;
+                if !self.player.knows_ability(ability) {
+                    return Err(ActionError::UnknownAbility(ability));
+                }
+                let turns = self.player.ability_cooldown(ability);
+                if turns > 0 {
+                    return Err(ActionError::AbilityCoolingDown { turns });
+                }
+                self.require_something_to_purge(ability)?;
+                if ability_def(&ability).targeting == Targeting::OneContact {
+                    match &self.activity {
+                        Activity::Combat(contacts) if target < contacts.len() => {}
+                        _ => return Err(ActionError::NoSuchTarget(target)),
+                    }
+                }
+                self.spend_ram(self.ability_cost(ability))?;
+                self.use_ability(ability, target);
+            }
+            Action::Escape => {
+                self.require_combat()?;
+                self.escape();
+            }
+            Action::ContinueConversation => {
+                match &mut self.activity {
+                    Activity::Conversing(convo) => {
+                        if !convo.next_line(&self.player) {
+                            return Err(ActionError::WrongActivity);
+                        }
+                    }
+                    _ => return Err(ActionError::WrongActivity),
+                }
+                self.apply_node_effects();
+            }
+            Action::ChooseResponse(index) => {
+                match &mut self.activity {
+                    Activity::Conversing(convo) => {
+                        if !convo.choose(index, &self.player) {
+                            return Err(ActionError::ResponseUnavailable);
+                        }
+                    }
+                    _ => return Err(ActionError::WrongActivity),
+                }
+                self.apply_node_effects();
+            }
+            Action::EndConversation => match &self.activity {
+                Activity::Conversing(convo) if convo.done() => {
+                    let id = convo.id.clone();
+                    self.activity = Activity::FreeRoam;
+                    self.report(Outcome::ConversationEnded);
+                    self.emit(GameEvent::ConversationFinished(id));
+                }
+                _ => return Err(ActionError::WrongActivity),
+            },
+            Action::ClaimQuestReward(quest_id) => {
+                self.require_free_roam()?;
+                match self.player.quests.get_mut(&quest_id) {
+                    Some(quest) if quest.reward_waiting() => quest.claim_reward(),
+                    _ => return Err(ActionError::NoRewardToClaim),
+                }
+                let rewards = quest_def(&quest_id)
+                    .map(|def| def.rewards.clone())
+                    .unwrap_or_default();
+                self.report(Outcome::QuestRewardClaimed {
+                    quest: quest_id,
+                    rewards: rewards.clone(),
+                });
+                for reward in rewards.iter() {
+                    self.grant_quest_reward(reward);
+                }
+            }
+            Action::Respawn => {
+                if !matches!(self.activity, Activity::Flatlined { .. }) {
+                    return Err(ActionError::WrongActivity);
+                }
+                if self.settings.ironman {
+                    return Err(ActionError::RunOver);
+                }
+                self.player.hp.value = self.player.hp.upper_limit;
+                self.activity = Activity::FreeRoam;
+                self.report(Outcome::Respawned);
+            }
+        }
+        self.update_quests();
+        self.plan_intents();
+        let killed_by = self.killed_by.take();
+        if self.player.hp.value <= 0 && !matches!(self.activity, Activity::Flatlined { .. }) {
+            self.flatline(killed_by);
+        }
+        Ok(())
+    }
+
+    /// content isn't saved with the game, hand it over after creating or loading one
+    pub fn set_dialogue(&mut self, dialogue: DialogueLibrary) {
+        self.dialogue = dialogue;
+    }
+
+    pub fn dialogue(&self) -> &DialogueLibrary {
+        &self.dialogue
+    }
+
+    /// Intel on the current network trickles in over real time,
+    /// it is banked when the next action is applied
+    pub fn accrue_intel(&mut self, seconds: f32) {
+        self.pending_idle_seconds += seconds;
+    }
+
+    /// intel on the current network, including any that hasn't been banked yet
+    pub fn current_intel(&self) -> f32 {
+        self.player
+            .net_stats
+            .get(&self.current_net)
+            .unwrap()
+            .total_intel
+            + self.pending_idle_seconds
+    }
+
+    /// everything needed to play this run back from the start
+    pub fn replay(&self) -> Replay {
+        Replay {
+            version: REPLAY_VERSION,
+            seed: self.seed,
+            settings: self.settings.clone(),
+            actions: self.log.clone(),
+        }
+    }
+
+    /// how the run went, for the records once an ironman run is over
+    pub fn run_record(&self) -> RunRecord {
+        RunRecord {
+            seed: self.seed,
+            player_name: self.player.name.clone(),
+            turns: self.turn,
+            level: self.player.level(),
+            kills: self.player.stats.kills,
+            credits: self.player.credits,
+            killed_by: match &self.activity {
+                Activity::Flatlined { killed_by } => killed_by.clone(),
+                _ => None,
+            },
+        }
+    }
+
+    pub fn network_available(&self, net: &Networks) -> bool {
+        match net {
+            // internet always available
+            Networks::Internet => true,
+            // SIPRnet only after you unlock it
+            Networks::SIPRnet => self.player.has_flag(&PlayerFlag::UnlockedNetworkSIPR),
+        }
+    }
+
+    /// what the player still has to do to get onto `net`, `None` once they can
+    pub fn unlock_hint(&self, net: &Networks) -> Option<String> {
+        if self.network_available(net) {
+            return None;
+        }
+        match net {
+            Networks::Internet => None,
+            Networks::SIPRnet => {
+                if self.player.has_flag(&PlayerFlag::MetSiprContact) {
+                    Some(format!(
+                        "buy credentials at the shop for {}c, or get good enough to forge your own and find your insider again",
+                        SIPR_CREDENTIALS_COST
+                    ))
+                } else {
+                    let internet_level = self
+                        .player
+                        .net_stats
+                        .get(&Networks::Internet)
+                        .unwrap()
+                        .intel_level(&Networks::Internet);
+                    if internet_level < SIPR_INTEL_LEVEL {
+                        Some(format!(
+                            "reach intel level {} on the Internet (now {})",
+                            SIPR_INTEL_LEVEL, internet_level
+                        ))
+                    } else {
+                        Some(
+                            "keep searching the Internet, someone on the inside has noticed you"
+                                .to_string(),
+                        )
+                    }
+                }
+            }
+        }
+    }
+
+    /// credentials the shop has for sale, if any
+    pub fn network_access_price(&self, net: &Networks) -> Option<i32> {
+        match net {
+            Networks::SIPRnet
+                if self.player.has_flag(&PlayerFlag::MetSiprContact)
+                    && !self.network_available(net) =>
+            {
+                Some(SIPR_CREDENTIALS_COST)
+            }
+            _ => None,
+        }
+    }
+
+    /// flags that stand for a network go through `unlock_network` so the player hears about it
+    fn enable_flag(&mut self, flag: PlayerFlag) {
+        match flag {
+            PlayerFlag::UnlockedNetworkSIPR => self.unlock_network(&Networks::SIPRnet),
+            flag => self.player.enable_flag(flag),
+        }
+    }
+
+    fn unlock_network(&mut self, net: &Networks) {
+        if let Networks::SIPRnet = net {
+            if !self.player.has_flag(&PlayerFlag::UnlockedNetworkSIPR) {
+                self.player.enable_flag(PlayerFlag::UnlockedNetworkSIPR);
+                self.report(Outcome::NetworkUnlocked(Networks::SIPRnet));
+            }
+        }
+    }
+
+    fn report(&mut self, outcome: Outcome) {
+        self.outcomes.push(outcome);
+    }
+
+    fn require_free_roam(&self) -> Result<(), ActionError> {
+        match self.activity {
+            Activity::FreeRoam => Ok(()),
+            _ => Err(ActionError::WrongActivity),
+        }
+    }
+
+    fn require_combat(&self) -> Result<(), ActionError> {
+        match self.activity {
+            Activity::Combat(_) => Ok(()),
+            _ => Err(ActionError::WrongActivity),
+        }
+    }
+
+    /// a purge needs something to strip off, or it would only burn RAM and a cooldown
+    pub fn require_something_to_purge(&self, ability: AbilityType) -> Result<(), ActionError> {
+        for effect in ability_def(&ability).effects.iter() {
+            if let AbilityEffect::Purge(buff) = *effect {
+                if self.player.buffs.has_buff(buff).is_none() {
+                    return Err(ActionError::NothingToPurge(buff));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn require_shopping(&self) -> Result<(), ActionError> {
+        match self.activity {
+            Activity::Interacting(InteractionType::BasicShop) => Ok(()),
+            _ => Err(ActionError::WrongActivity),
+        }
+    }
+
+    fn require_intel_level(&self, needed: i32) -> Result<(), ActionError> {
+        if self.intel_level() < needed {
+            return Err(ActionError::IntelTooLow { needed });
+        }
+        Ok(())
+    }
+
+    fn spend_ram(&mut self, cost: i32) -> Result<(), ActionError> {
+        if self.player.ram.value < cost {
+            return Err(ActionError::NotEnoughRam { needed: cost });
+        }
+        self.player.ram.change_by(-cost);
+        Ok(())
+    }
+
+    fn do_turn(&mut self) {
+        self.turn += 1;
+        self.player.buffs.do_turn();
+        for turns in self.player.ability_cooldowns.values_mut() {
+            *turns = turns.saturating_sub(1);
+        }
+        self.player.ability_cooldowns.retain(|_, turns| *turns > 0);
+        let regen = self.player.ram_regen();
+        if regen > 0 {
+            self.player.ram.change_by(regen);
+        }
+    }
+
+    /// carry out an ability's effects, its cost and cooldown already checked
+    fn use_ability(&mut self, ability: AbilityType, target: usize) {
+        let def = ability_def(&ability);
+        self.report(Outcome::AbilityUsed(ability));
+        for effect in def.effects.iter() {
+            match *effect {
+                AbilityEffect::Damage(scale) => match def.targeting {
+                    Targeting::OneContact => self.hack_contacts(Some(target), scale),
+                    Targeting::AllContacts => self.hack_contacts(None, scale),
+                    Targeting::Player => {}
+                },
+                AbilityEffect::Buff(buff, turns) => {
+                    self.player.buffs.add_buff(buff, turns);
+                    self.report(Outcome::BuffGained { buff, turns });
+                }
+                AbilityEffect::Heal(amount) => {
+                    self.player.hp.change_by(amount);
+                    self.report(Outcome::HpRecovered(amount));
+                }
+                AbilityEffect::Purge(buff) => {
+                    if self.player.buffs.remove_buff(buff) {
+                        self.report(Outcome::BuffPurged(buff));
+                    }
+                }
+            }
+        }
+        if def.ends_round {
+            self.contacts_act();
+        }
+        self.clear_defeated_contacts();
+        if def.ends_round {
+            self.do_turn();
+        }
+        if def.cooldown > 0 {
+            self.player.ability_cooldowns.insert(ability, def.cooldown);
+        }
+    }
+
+    /// RAM an ability costs, after perks and whatever is running on the player
+    pub fn ability_cost(&self, ability: AbilityType) -> i32 {
+        let mut cost = ability_def(&ability).ram_cost;
+        if ability == AbilityType::Overclock && self.player.has_perk(Perk::Overclocker) {
+            cost -= OVERCLOCKER_DISCOUNT;
+        }
+        self.player.buffs.get_ability_ram(cost)
+    }
+
+    /// hack one contact, or every contact (`None`) for an even share of the damage
+    fn hack_contacts(&mut self, target: Option<usize>, scale: f32) {
+        let mut outcomes = vec![];
+        if let Activity::Combat(contacts) = &mut self.activity {
+            let share = match target {
+                Some(_) => 1,
+                None => contacts.len() as i32,
+            };
+            for (index, contact) in contacts.iter_mut().enumerate() {
+                if target.is_some_and(|target| target != index) {
+                    continue;
+                }
+                // calculate dmg to hostile
+                let min_dmg_to_hostile =
+                    ((2 * self.player.skills.hacking) - contact.skills.security).max(0);
+                let max_dmg_to_hostile =
+                    ((4 * self.player.skills.hacking) - (contact.skills.security / 2)).max(1);
+                let full_dmg = self.rng.gen_range(min_dmg_to_hostile..max_dmg_to_hostile);
+                let dmg_to_hostile = contact
+                    .buffs
+                    .get_dmg_taken((full_dmg as f32 * scale / share as f32).ceil() as i32);
+                // buff dmg
+                let buff_dmg = self.player.buffs.get_buff_dmg(dmg_to_hostile);
+
+                outcomes.push(Outcome::DamageDealt {
+                    target: contact.name.clone(),
+                    amount: dmg_to_hostile,
+                    bonus: buff_dmg,
+                });
+                contact.hp.change_by(-(dmg_to_hostile + buff_dmg));
+            }
+        } else {
+            panic!("hack_contacts() called not in combat")
+        }
+        self.outcomes.append(&mut outcomes);
+    }
+
+    /// every contact still standing carries out its telegraphed move
+    fn contacts_act(&mut self) {
+        let mut outcomes = vec![];
+        if let Activity::Combat(contacts) = &mut self.activity {
+            let fighting = contacts.len();
+            let mut summoned = vec![];
+            for contact in contacts.iter_mut().filter(|contact| contact.hp.value > 0) {
+                let room_to_summon = fighting + summoned.len() < MAX_CONTACTS;
+                let (outcome, spawned) =
+                    contact_turn(contact, &mut self.player, &mut self.rng, room_to_summon);
+                outcomes.push(outcome);
+                summoned.extend(spawned);
+                if self.player.hp.value <= 0 {
+                    // nobody else needs a turn, this one flatlined the player
+                    self.killed_by = Some(contact.name.clone());
+                    break;
+                }
+            }
+            contacts.extend(summoned);
+        }
+        self.outcomes.append(&mut outcomes);
+    }
+
+    /// contacts without a move lined up pick one, so the player can see it coming
+    fn plan_intents(&mut self) {
+        if let Activity::Combat(contacts) = &mut self.activity {
+            // summons already planned take up room in the fight
+            let mut fighting = contacts.len()
+                + contacts
+                    .iter()
+                    .filter(|contact| {
+                        contact.intent.is_some_and(|intent| {
+                            intent.ability == ContactAbility::SummonSubprocess
+                        })
+                    })
+                    .count();
+            for contact in contacts
+                .iter_mut()
+                .filter(|contact| contact.intent.is_none())
+            {
+                let intent = plan_intent(
+                    contact,
+                    &self.player,
+                    &mut self.rng,
+                    fighting < MAX_CONTACTS,
+                );
+                if intent.ability == ContactAbility::SummonSubprocess {
+                    fighting += 1;
+                }
+                contact.intent = Some(intent);
+            }
+        }
+    }
+
+    /// reward the player for every contact at 0 HP and take them out of the fight
+    fn clear_defeated_contacts(&mut self) {
+        let defeated: Vec<Contact> = match &mut self.activity {
+            Activity::Combat(contacts) => {
+                let (defeated, alive) = std::mem::take(contacts)
+                    .into_iter()
+                    .partition(|contact| contact.hp.value <= 0);
+                *contacts = alive;
+                defeated
+            }
+            _ => return,
+        };
+        if matches!(&self.activity, Activity::Combat(contacts) if contacts.is_empty()) {
+            self.activity = Activity::FreeRoam;
+        }
+        let mut xp_earned = 0;
+        for contact in defeated.iter() {
+            // killed an enemy!
+            let xp = contact.reward();
+            self.report(Outcome::ContactDefeated {
+                name: contact.name.clone(),
+                xp,
+            });
+            xp_earned += xp;
+            if !contact.is_subprocess() {
+                self.roll_loot(contact);
+            }
+        }
+        self.gain_xp(xp_earned);
+        for contact in defeated.iter().filter(|contact| !contact.is_subprocess()) {
+            self.emit(GameEvent::ContactDefeated {
+                net: self.current_net.clone(),
+                level: contact.level,
+            });
+        }
+    }
+
+    /// every entry in the network's loot table gets its own roll
+    fn roll_loot(&mut self, contact: &Contact) {
+        let quality = drop_quality(contact.level, &self.current_net);
+        for entry in loot_table(&self.current_net) {
+            if let LootDrop::Blueprint(blueprint) = entry.drop {
+                if self.player.blueprints.contains(&blueprint) {
+                    continue;
+                }
+            }
+            if !roll_encounter(&mut self.rng, 1.0 - entry.chance(quality)) {
+                continue;
+            }
+            let loot = Loot::from_drop(&entry.drop, quality);
+            match loot {
+                // paid out once the drop has been reported
+                Loot::Credits(_) => {}
+                Loot::Item(item) => self.player.inventory.add(item, 1),
+                Loot::DataFragments(amount) => self.player.data_fragments += amount,
+                Loot::Blueprint(blueprint) => self.player.blueprints.push(blueprint),
+            }
+            self.report(Outcome::LootDropped {
+                name: contact.name.clone(),
+                loot: loot.clone(),
+            });
+            if let Loot::Credits(amount) = loot {
+                self.earn_credits(amount);
+            }
+        }
+    }
+
+    fn escape(&mut self) {
+        self.activity = Activity::FreeRoam;
+        self.report(Outcome::Escaped);
+        self.emit(GameEvent::Escaped(self.current_net.clone()));
+    }
+
+    fn use_item(&mut self, item: ItemType) {
+        self.report(Outcome::ItemUsed(item));
+        match item {
+            ItemType::RamPatch => {
+                self.player.ram.change_by(RAM_PATCH_AMOUNT);
+                self.report(Outcome::RamRecovered(RAM_PATCH_AMOUNT));
+            }
+            ItemType::RepairKit => {
+                self.player.hp.change_by(REPAIR_KIT_AMOUNT);
+                self.report(Outcome::HpRecovered(REPAIR_KIT_AMOUNT));
+            }
+            ItemType::Exploit => {
+                let mut outcomes = vec![];
+                if let Activity::Combat(contacts) = &mut self.activity {
+                    for contact in contacts.iter_mut() {
+                        contact.hp.change_by(-EXPLOIT_DAMAGE);
+                        outcomes.push(Outcome::DamageDealt {
+                            target: contact.name.clone(),
+                            amount: EXPLOIT_DAMAGE,
+                            bonus: 0,
+                        });
+                    }
+                }
+                self.outcomes.append(&mut outcomes);
+                self.clear_defeated_contacts();
+            }
+            ItemType::Smoke => self.escape(),
+        }
+    }
+
+    /// (intel level, progress towards the next) on the current network, unbanked intel included
+    pub fn current_intel_level(&self) -> (i32, f32) {
+        intel_level(self.current_intel(), &self.current_net)
+    }
+
+    /// intel level on the network the player is on, banked intel only; actions bank idle
+    /// intel before they check it, so anything shown ahead of time wants `current_intel_level`
+    pub fn intel_level(&self) -> i32 {
+        self.player
+            .net_stats
+            .get(&self.current_net)
+            .unwrap()
+            .intel_level(&self.current_net)
+    }
+
+    /// maybe run into someone while doing `task`, true if a conversation started
+    fn roll_npc_encounter(&mut self, task: Tasks) -> bool {
+        let intel_level = self.intel_level();
+        for encounter in ENCOUNTERS.iter() {
+            let eligible = encounter.net == self.current_net
+                && encounter.tasks.contains(&task)
+                && intel_level >= encounter.min_intel_level
+                && encounter
+                    .conditions
+                    .iter()
+                    .all(|condition| condition.met(&self.player))
+                && !(encounter.once
+                    && self
+                        .player
+                        .encounters_met
+                        .iter()
+                        .any(|met| met == encounter.conversation));
+            if !eligible {
+                continue;
+            }
+            if !roll_encounter(&mut self.rng, 1.0 - encounter.chance) {
+                continue;
+            }
+            // content that failed to load just never shows up; rolling first means a missing
+            // file only changes the runs where this NPC would actually have turned up
+            let Some(def) = self.dialogue.get(encounter.conversation) else {
+                return false;
+            };
+            let convo = def.start();
+            if encounter.once {
+                self.player
+                    .encounters_met
+                    .push(encounter.conversation.to_string());
+            }
+            self.report(Outcome::NpcEncountered(encounter.name.to_string()));
+            self.start_conversation(convo);
+            return true;
+        }
+        false
+    }
+
+    fn start_conversation(&mut self, convo: Conversation) {
+        self.activity = Activity::Conversing(convo);
+        self.apply_node_effects();
+    }
+
+    /// run the effects of the node the conversation just reached
+    fn apply_node_effects(&mut self) {
+        let (id, effects) = match &self.activity {
+            Activity::Conversing(convo) => (convo.id.clone(), convo.node_effects().to_vec()),
+            _ => return,
+        };
+        for effect in effects {
+            self.apply_dialogue_effect(effect);
+        }
+        // a shop or a fight ends the conversation as well
+        if !matches!(self.activity, Activity::Conversing(_)) {
+            self.emit(GameEvent::ConversationFinished(id));
+        }
+    }
+
+    fn apply_dialogue_effect(&mut self, effect: DialogueEffect) {
+        match effect {
+            DialogueEffect::SetFlag(flag) => self.enable_flag(flag),
+            DialogueEffect::ClearFlag(flag) => self.player.disable_flag(&flag),
+            DialogueEffect::StartQuest(quest_id) => self.start_quest(&quest_id),
+            DialogueEffect::AdvanceQuest(quest_id) => {
+                let Some(quest) = self.player.quests.get_mut(&quest_id) else {
+                    return;
+                };
+                if quest.is_active() && !quest.is_finished() {
+                    quest.complete_objective();
+                    self.objective_completed(&quest_id);
+                }
+            }
+            DialogueEffect::GiveCredits(amount) => {
+                self.report(Outcome::CreditsReceived(amount));
+                self.earn_credits(amount);
+            }
+            DialogueEffect::TakeCredits(amount) => {
+                let paid = amount.min(self.player.credits).max(0);
+                self.report(Outcome::CreditsPaid(paid));
+                self.spend_credits(paid);
+            }
+            DialogueEffect::GiveXp(amount) => {
+                self.report(Outcome::XpGained(amount));
+                self.gain_xp(amount);
+            }
+            DialogueEffect::GiveItem(item) => {
+                self.player.inventory.add(item, 1);
+                self.report(Outcome::ItemReceived(item));
+            }
+            DialogueEffect::ApplyBuff(buff, duration) => {
+                self.player.buffs.add_buff(buff, duration);
+                self.report(Outcome::BuffApplied(buff));
+            }
+            DialogueEffect::OpenShop => self.go_shopping(),
+            DialogueEffect::StartCombat => {
+                let level = self.contact_level(self.current_net.difficulty());
+                let contact = Contact::new(level, &self.current_net, &mut self.rng);
+                self.report(Outcome::ConversationTurnedHostile {
+                    contact: contact.name.clone(),
+                });
+                self.activity = Activity::Combat(vec![contact]);
+            }
+        }
+    }
+
+    fn flatline(&mut self, killed_by: Option<String>) {
+        self.activity = Activity::Flatlined {
+            killed_by: killed_by.clone(),
+        };
+        let intel_before = self.current_intel();
+        self.emit(GameEvent::Flatlined {
+            net: self.current_net.clone(),
+            reset_intel: self.settings.reset_intel && !self.settings.ironman,
+        });
+        let intel_lost = intel_before - self.current_intel();
+        if self.settings.ironman {
+            self.report(Outcome::Flatlined {
+                killed_by,
+                credits_lost: 0,
+                intel_lost: 0.0,
+            });
+            self.report(Outcome::RunEnded);
+            return;
+        }
+        let penalty = self.settings.credit_penalty.clamp(0.0, 1.0);
+        let credits_lost = (self.player.credits as f32 * penalty).ceil() as i32;
+        self.player.credits -= credits_lost;
+        if self.settings.clear_buffs {
+            self.player.buffs.clear();
+        }
+        self.report(Outcome::Flatlined {
+            killed_by,
+            credits_lost,
+            intel_lost,
+        });
+    }
+
+    fn start_quest(&mut self, quest_id: &QuestID) {
+        if let Some(quest) = self.player.quests.get_mut(quest_id) {
+            if !quest.is_started() {
+                quest.start();
+                self.report(Outcome::QuestStarted(quest_id.clone()));
+            }
+        }
+    }
+
+    /// tell everything keeping track of the run what just happened
+    fn emit(&mut self, event: GameEvent) {
+        self.player.stats.on_event(&event);
+        if let Some(stats) = event
+            .network()
+            .and_then(|net| self.player.net_stats.get_mut(net))
+        {
+            stats.on_event(&event);
+        }
+        // quests go last, so they see the stats they might be waiting on already updated
+        let mut completed = vec![];
+        for (quest_id, quest) in self.player.quests.iter_mut() {
+            if quest.record(&event) {
+                completed.push(quest_id.clone());
+            }
+        }
+        for quest_id in completed {
+            self.objective_completed(&quest_id);
+        }
+    }
+
+    /// report the next objective, or hand out the reward if that was the last one
+    fn objective_completed(&mut self, quest_id: &QuestID) {
+        let Some(quest) = self.player.quests.get(quest_id) else {
+            return;
+        };
+        if let Some(next) = quest.objective() {
+            self.report(Outcome::ObjectiveCompleted {
+                quest: quest_id.clone(),
+                next: next.to_string(),
+            });
+            return;
+        }
+        let Some(def) = quest_def(quest_id) else {
+            return;
+        };
+        let claimed = !self.settings.manual_quest_rewards || def.rewards.is_empty();
+        if let Some(quest) = self.player.quests.get_mut(quest_id) {
+            quest.mark_completed(self.turn);
+            if claimed {
+                quest.claim_reward();
+            }
+        }
+        self.report(Outcome::QuestCompleted {
+            quest: quest_id.clone(),
+            rewards: def.rewards.clone(),
+            claimed,
+        });
+        if claimed {
+            for reward in def.rewards.iter() {
+                self.grant_quest_reward(reward);
+            }
+        }
+        for follow_up in def.follow_ups.iter() {
+            self.start_quest(follow_up);
+        }
+    }
+
+    /// already listed in the completion summary, so only knock-on outcomes are reported
+    fn grant_quest_reward(&mut self, reward: &QuestReward) {
+        match reward {
+            QuestReward::XP(amount) => self.gain_xp(*amount),
+            QuestReward::Credits(amount) => self.player.credits += amount,
+            QuestReward::Item(item, count) => self.player.inventory.add(*item, *count),
+            QuestReward::Buff(buff, duration) => self.player.buffs.add_buff(*buff, *duration),
+            QuestReward::Upgrade(upgrade_type) => {
+                if let Some(upgrade) = self.player.upgrades.get_mut(upgrade_type) {
+                    if !upgrade.available {
+                        upgrade.available = true;
+                        self.report(Outcome::UpgradeUnlocked(upgrade_type.clone()));
+                    }
+                }
+            }
+            QuestReward::Flag(flag) => self.enable_flag(flag.clone()),
+            QuestReward::Network(net) => self.unlock_network(net),
+            QuestReward::Ability(ability) => {
+                if !self.player.knows_ability(*ability) {
+                    self.player.abilities.push(*ability);
+                    self.report(Outcome::AbilityLearned(*ability));
+                }
+            }
+        }
+    }
+
+    /// catch quests up on where the player stands after an action
+    fn update_quests(&mut self) {
+        for net in [Networks::Internet, Networks::SIPRnet] {
+            let level = self.player.net_stats[&net].intel_level(&net);
+            self.emit(GameEvent::IntelLevel { net, level });
+        }
+        let ready: Vec<QuestID> = self
+            .player
+            .quests
+            .values()
+            .filter(|quest| {
+                quest.is_started() && !quest.is_active() && quest.prerequisites_met(&self.player)
+            })
+            .map(|quest| quest.quest_id.clone())
+            .collect();
+        for quest_id in ready {
+            if let Some(quest) = self.player.quests.get_mut(&quest_id) {
+                quest.activate();
+            }
+        }
+    }
+
+    fn gain_xp(&mut self, amount: i32) {
+        let levels_gained = self.player.add_xp(amount);
+        let level = self.player.level();
+        for gained in (level - levels_gained + 1)..=level {
+            self.report(Outcome::LevelUp {
+                level: gained,
+                skill_points: SKILL_POINTS_PER_LEVEL,
+                perk_available: gained % LEVELS_PER_PERK == 0,
+            });
+        }
+    }
+
+    fn choose_perk(&mut self, perk: Perk) {
+        self.player.perks.push(perk);
+        if perk == Perk::Hardened {
+            self.player.hp.upper_limit += 25;
+            self.player.hp.value += 25;
+        }
+        self.report(Outcome::PerkChosen(perk));
+    }
+
+    /// how many committed points a respec would free up
+    pub fn respec_refund(&self) -> i32 {
+        SkillType::ALL
+            .iter()
+            .map(|skill| (self.player.skills.get(skill) - skill.floor()).max(0))
+            .sum()
+    }
+
+    /// credits from a task, after perks
+    fn task_credits(&self, base: i32) -> i32 {
+        if self.player.has_perk(Perk::DataHound) {
+            (base as f32 * 1.25).ceil() as i32
+        } else {
+            base
+        }
+    }
+
+    /// contacts keep pace with the player, harder networks field stronger ones,
+    /// and so does digging deeper into a network
+    fn contact_level(&self, difficulty: f32) -> i32 {
+        let player_strength = self.player.total_skill_points() as f32 / BASE_SKILL_POINTS as f32;
+        ((player_strength * difficulty).round() as i32).max(1) + self.intel_level() / 2
+    }
+
+    /// knowing a network makes tasks on it go well more often
+    fn task_success_chance(&self, base: f32) -> f32 {
+        (base + INTEL_SUCCESS_BONUS * self.intel_level() as f32).min(0.95)
+    }
+
+    fn do_task(&mut self, task: Tasks) {
+        let difficulty = self.current_net.difficulty();
+
+        match task {
+            Tasks::Search => {
+                self.do_task_search(difficulty);
+            }
+            Tasks::Datamine => {
+                self.do_task_datamine(difficulty);
+            }
+            Tasks::Recover => self.do_task_recovery(),
+            Tasks::Exfiltrate => self.do_task_exfiltrate(difficulty),
+        }
+    }
+
+    /// pay the player for work, loot or a deal; quest rewards go straight in without this
+    fn earn_credits(&mut self, amount: i32) {
+        self.player.credits += amount;
+        self.emit(GameEvent::CreditsEarned(amount));
+    }
+
+    fn spend_credits(&mut self, amount: i32) {
+        self.player.credits -= amount;
+        self.emit(GameEvent::CreditsSpent(amount));
+    }
+
+    fn task_succeeded(&mut self, task: Tasks) {
+        self.emit(GameEvent::TaskSucceeded {
+            task,
+            net: self.current_net.clone(),
+        });
+    }
+
+    fn go_shopping(&mut self) {
+        // gracefully transition the player into the shopping state
+        self.activity = Activity::Interacting(InteractionType::BasicShop);
+        self.report(Outcome::ShopEntered(self.current_net.clone()));
+        self.emit(GameEvent::ShopVisited);
+    }
+
+    fn do_task_recovery(&mut self) {
+        let is_max_ram = self.player.ram.upper_limit == self.player.ram.value;
+        let is_max_hp = self.player.hp.upper_limit == self.player.hp.value;
+        if (is_max_hp) && (is_max_ram) {
+            self.report(Outcome::AlreadyRecovered);
+            return;
+        }
+
+        self.do_turn();
+
+        let bonus = if self.player.has_perk(Perk::QuickRecovery) {
+            5
+        } else {
+            0
+        };
+
+        // Recover RAM
+        if !is_max_ram {
+            let reward = self.rng.gen_range(7..22) + bonus;
+            self.player.ram.change_by(reward);
+            self.report(Outcome::RamRecovered(reward));
+        }
+
+        // Recover HP
+        if !is_max_hp {
+            let reward = self.rng.gen_range(5..16) + bonus;
+            self.player.hp.change_by(reward);
+            self.report(Outcome::HpRecovered(reward));
+        }
+        self.task_succeeded(Tasks::Recover);
+    }
+
+    fn do_task_datamine(&mut self, difficulty: f32) {
+        self.do_turn();
+        if self.roll_npc_encounter(Tasks::Datamine) {
+            return;
+        }
+        let success_chance = self.task_success_chance(0.6);
+        let roll_success: f32 = self.rng.gen();
+        if roll_encounter(&mut self.rng, 1.0 - success_chance) {
+            // success - earn credits
+            let reward_amount = self.task_credits((roll_success * difficulty * 14.5).ceil() as i32);
+            self.report(Outcome::CreditsFound {
+                task: Tasks::Datamine,
+                chance: success_chance,
+                amount: reward_amount,
+            });
+            self.earn_credits(reward_amount);
+            self.task_succeeded(Tasks::Datamine);
+        } else {
+            // "fail" - combat
+            let level = self.contact_level(difficulty);
+            let new_contact = Contact::new(level, &self.current_net, &mut self.rng);
+            self.report(Outcome::CombatStarted {
+                chance: 1.0 - success_chance,
+                contact: new_contact.name.clone(),
+            });
+            self.activity = Activity::Combat(vec![new_contact]);
+        }
+    }
+
+    fn do_task_exfiltrate(&mut self, difficulty: f32) {
+        self.do_turn();
+        let success_chance = self.task_success_chance(0.4);
+        let roll_success: f32 = self.rng.gen();
+        if roll_encounter(&mut self.rng, 1.0 - success_chance) {
+            // success - a big haul
+            let reward_amount = self.task_credits((roll_success * difficulty * 40.0).ceil() as i32);
+            let fragments = (difficulty * 2.0).ceil() as i32;
+            self.player.data_fragments += fragments;
+            self.report(Outcome::CreditsFound {
+                task: Tasks::Exfiltrate,
+                chance: success_chance,
+                amount: reward_amount,
+            });
+            self.report(Outcome::DataExfiltrated(fragments));
+            self.earn_credits(reward_amount);
+            self.task_succeeded(Tasks::Exfiltrate);
+        } else {
+            // caught on the way out, by something nastier than usual and its backup
+            let level = self.contact_level(difficulty);
+            let new_contact = Contact::new(level + 1, &self.current_net, &mut self.rng);
+            let backup = Contact::new(level, &self.current_net, &mut self.rng);
+            self.report(Outcome::CombatStarted {
+                chance: 1.0 - success_chance,
+                contact: new_contact.name.clone(),
+            });
+            self.report(Outcome::ContactJoined(backup.name.clone()));
+            self.activity = Activity::Combat(vec![new_contact, backup]);
+        }
+    }
+
+    fn do_task_search(&mut self, difficulty: f32) {
+        self.do_turn();
+        let roll_success: f32 = self.rng.gen();
+
+        // first-time encounters
+        match self.current_net {
+            Networks::Internet => {
+                if !self.player.has_flag(&PlayerFlag::DiscoveredShopBasic)
+                    && self.player.credits >= 100
+                    && roll_encounter(&mut self.rng, 0.2)
+                {
+                    // stumble across the shop
+                    self.report(Outcome::ShopDiscovered);
+                    self.go_shopping();
+                    return;
+                }
+            }
+            Networks::SIPRnet => {}
+        }
+        if self.roll_npc_encounter(Tasks::Search) {
+            return;
+        }
+
+        let success_chance = self.task_success_chance(0.8);
+        if roll_encounter(&mut self.rng, 1.0 - success_chance) {
+            // good thing - search success
+            let reward_amount = self.task_credits((roll_success * difficulty * 6.5).ceil() as i32);
+            self.report(Outcome::CreditsFound {
+                task: Tasks::Search,
+                chance: success_chance,
+                amount: reward_amount,
+            });
+            self.earn_credits(reward_amount);
+            self.task_succeeded(Tasks::Search);
+        } else {
+            // regen a bit of ram
+            let reward_amount: i32 = (roll_success * 7.5 + difficulty).ceil() as i32;
+            self.player.ram.change_by(reward_amount);
+            self.report(Outcome::NothingFound {
+                chance: 1.0 - success_chance,
+                ram: reward_amount,
+            });
+        }
+    }
+
+    fn buy_upgrade(&mut self, upgrade: PlayerUpgradeType) -> Result<(), ActionError> {
+        self.require_intel_level(upgrade_def(&upgrade).min_intel_level)?;
+        let cost = match self.player.upgrades.get(&upgrade) {
+            Some(up) if up.is_maxed() => return Err(ActionError::UpgradeMaxed),
+            Some(up) if up.available && self.player.meets_prerequisites(up.def()) => {
+                up.cost() as i32
+            }
+            _ => return Err(ActionError::UpgradeUnavailable),
+        };
+        if self.player.credits < cost {
+            return Err(ActionError::NotEnoughCredits { needed: cost });
+        }
+        self.spend_credits(cost);
+        self.do_upgrade_effect(&upgrade);
+        self.report(Outcome::UpgradeBought(upgrade.clone()));
+        self.emit(GameEvent::UpgradeBought(upgrade));
+        self.stock_unlocked_upgrades();
+        Ok(())
+    }
+
+    fn do_upgrade_effect(&mut self, upgrade: &PlayerUpgradeType) {
+        // increase upgrade level by 1 and apply the upgrade's effects
+        self.player.upgrades.get_mut(upgrade).unwrap().level += 1;
+        for effect in upgrade_def(upgrade).effects {
+            match *effect {
+                UpgradeEffect::MaxHp(amount) => {
+                    self.player.hp.upper_limit += amount;
+                    self.player.hp.value += amount;
+                }
+                UpgradeEffect::MaxRam(amount) => {
+                    self.player.ram.upper_limit += amount;
+                    self.player.ram.value += amount;
+                }
+                // caps and regen are worked out from upgrade levels when needed
+                UpgradeEffect::SkillCap(..) | UpgradeEffect::RamRegen(_) => {}
+            }
+        }
+    }
+
+    /// put upgrades whose prerequisites are now met up for sale
+    fn stock_unlocked_upgrades(&mut self) {
+        for def in CATALOG.iter() {
+            if !self.player.meets_prerequisites(def) {
+                continue;
+            }
+            let upgrade = self.player.upgrades.get_mut(&def.upgrade_type).unwrap();
+            if !upgrade.available {
+                upgrade.available = true;
+                self.report(Outcome::UpgradeUnlocked(def.upgrade_type.clone()));
+            }
+        }
+    }
+
+    fn current_net_stats_mut(&mut self) -> &mut NetStats {
+        self.player.net_stats.get_mut(&self.current_net).unwrap()
+    }
+}
+
+/// what a contact's behaviour lines up for its next turn, amounts rolled up front
+fn plan_intent(
+    contact: &Contact,
+    player: &Player,
+    rng: &mut GameRng,
+    room_to_summon: bool,
+) -> Intent {
+    let situation = Situation {
+        hp_left: contact.hp.value as f32 / contact.hp.upper_limit as f32,
+        fortified: contact
+            .buffs
+            .has_buff(BuffType::FirewallFortified)
+            .is_some(),
+        player_infected: player.buffs.has_buff(BuffType::MalwareInjected).is_some(),
+        player_ram: player.ram.value,
+        room_to_summon,
+    };
+    let ability = contact
+        .behaviour
+        .choose(&contact.abilities, &situation, rng);
+    let amount = match ability {
+        ContactAbility::Strike => strike_damage(contact, player, rng),
+        ContactAbility::DrainRam => rng.gen_range(3..6 + contact.level),
+        ContactAbility::SelfRepair => (contact.hp.upper_limit / 4).max(1),
+        _ => 0,
+    };
+    Intent { ability, amount }
+}
+
+/// the contact carries out the move it telegraphed, and the sub-process it brought in if any
+fn contact_turn(
+    contact: &mut Contact,
+    player: &mut Player,
+    rng: &mut GameRng,
+    room_to_summon: bool,
+) -> (Outcome, Option<Contact>) {
+    let intent = match contact.intent.take() {
+        Some(intent) => intent,
+        None => plan_intent(contact, player, rng, room_to_summon),
+    };
+    contact.buffs.do_turn();
+    let source = contact.name.clone();
+    match intent.ability {
+        ContactAbility::Strike => (contact_strike(contact, player, intent.amount), None),
+        ContactAbility::InjectMalware => {
+            player
+                .buffs
+                .add_buff(BuffType::MalwareInjected, MALWARE_DURATION);
+            (Outcome::MalwareInjected { source }, None)
+        }
+        ContactAbility::Fortify => {
+            contact
+                .buffs
+                .add_buff(BuffType::FirewallFortified, FORTIFY_DURATION);
+            (Outcome::ContactFortified(source), None)
+        }
+        ContactAbility::DrainRam => {
+            let amount = intent.amount.min(player.ram.value).max(0);
+            player.ram.change_by(-amount);
+            (Outcome::RamDrained { source, amount }, None)
+        }
+        // the fight filled up since it was planned, so it lashes out instead
+        ContactAbility::SummonSubprocess if !room_to_summon => {
+            let amount = strike_damage(contact, player, rng);
+            (contact_strike(contact, player, amount), None)
+        }
+        ContactAbility::SummonSubprocess => {
+            let spawned = contact.subprocess();
+            let outcome = Outcome::SubprocessSpawned {
+                source,
+                spawned: spawned.name.clone(),
+            };
+            (outcome, Some(spawned))
+        }
+        ContactAbility::SelfRepair => {
+            contact.hp.change_by(intent.amount);
+            (
+                Outcome::ContactRepaired {
+                    name: source,
+                    amount: intent.amount,
+                },
+                None,
+            )
+        }
+    }
+}
+
+/// how hard a contact's strike lands on the player
+fn strike_damage(contact: &Contact, player: &Player, rng: &mut GameRng) -> i32 {
+    let min_dmg_to_player = (2 + contact.skills.hacking - player.skills.security).max(0);
+    let max_dmg_to_player = (4 + contact.skills.hacking - (player.skills.security / 2)).max(1);
+    rng.gen_range(min_dmg_to_player..max_dmg_to_player)
+}
+
+/// a contact hits back at the player, through whatever firewall they've put up
+fn contact_strike(contact: &Contact, player: &mut Player, dmg: i32) -> Outcome {
+    let dmg_to_player = player.buffs.get_dmg_taken(dmg);
+    player.hp.change_by(-dmg_to_player);
+    Outcome::DamageTaken {
+        source: contact.name.clone(),
+        amount: dmg_to_player,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::player::xp_for_level;
+
+    /// a run's worth of actions, some of which get rejected along the way
+    fn script() -> Vec<Action> {
+        let hack = Action::UseAbility {
+            ability: AbilityType::Hack,
+            target: 0,
+        };
+        let mut actions = vec![];
+        for _ in 0..15 {
+            actions.extend([
+                Action::DoTask(Tasks::Search),
+                Action::DoTask(Tasks::Datamine),
+                hack.clone(),
+                hack.clone(),
+                Action::Escape,
+                Action::Respawn,
+            ]);
+        }
+        actions
+    }
+
+    /// everything that gets saved
+    fn snapshot(game: &Game) -> String {
+        ron::to_string(game).unwrap()
+    }
+
+    #[test]
+    fn the_same_seed_and_actions_play_out_the_same() {
+        let settings = GameSettings {
+            manual_quest_rewards: true,
+            ..GameSettings::default()
+        };
+        let mut first = Game::with_settings(7, settings.clone());
+        let mut second = Game::with_settings(7, settings);
+        for action in script() {
+            assert_eq!(first.apply(action.clone()), second.apply(action));
+        }
+        assert!(first.turn > 1);
+        assert_eq!(snapshot(&first), snapshot(&second));
+    }
+
+    #[test]
+    fn a_defeated_subprocess_counts_for_nothing() {
+        let mut game = Game::new(3);
+        let mut swarm = Contact::new(2, &Networks::Internet, &mut game.rng);
+        let mut subprocess = swarm.subprocess();
+        subprocess.hp.value = 0;
+        game.activity = Activity::Combat(vec![subprocess]);
+        game.clear_defeated_contacts();
+        assert_eq!(game.player.stats.kills, 0);
+        assert_eq!(game.player.xp, 0);
+        assert_eq!(game.current_intel(), 0.0);
+
+        swarm.hp.value = 0;
+        game.activity = Activity::Combat(vec![swarm]);
+        game.clear_defeated_contacts();
+        assert_eq!(game.player.stats.kills, 1);
+        assert!(game.current_intel() > 0.0);
+    }
+
+    #[test]
+    fn a_task_that_finishes_an_objective_pays_nothing_towards_the_next() {
+        let mut game = Game::new(11);
+        let contract = "fixer_contract".to_string();
+        game.start_quest(&contract);
+        for _ in 0..500 {
+            let action = match game.activity {
+                Activity::Combat(_) => Action::Escape,
+                Activity::Flatlined { .. } => Action::Respawn,
+                Activity::Conversing(_) => Action::EndConversation,
+                Activity::Interacting(_) => Action::LeaveShop,
+                Activity::FreeRoam => Action::DoTask(Tasks::Datamine),
+            };
+            let _ = game.apply(action);
+            let quest = &game.player.quests[&contract];
+            if quest.step() == 1 {
+                // the third datamine paid out, but that was for the first objective
+                assert_eq!(quest.objective_progress(), Some((0, 150)));
+                return;
+            }
+        }
+        panic!("never finished three datamines");
+    }
+
+    #[test]
+    fn escaping_costs_intel_but_never_takes_it_below_zero() {
+        let mut game = Game::new(5);
+        game.current_net_stats_mut().total_intel = 10.0;
+        game.escape();
+        assert_eq!(game.current_intel(), 3.0);
+        game.escape();
+        assert_eq!(game.current_intel(), 0.0);
+    }
+
+    #[test]
+    fn using_an_item_in_a_fight_takes_the_turn() {
+        let mut game = Game::new(9);
+        let contact = Contact::new(1, &Networks::Internet, &mut game.rng);
+        game.activity = Activity::Combat(vec![contact]);
+        game.player.inventory.add(ItemType::RepairKit, 1);
+        let turn = game.turn;
+        let outcomes = game.apply(Action::UseItem(ItemType::RepairKit)).unwrap();
+        assert_eq!(game.turn, turn + 1);
+        // the contact got its move in
+        assert!(outcomes.len() > 2);
+
+        game.player.inventory.add(ItemType::RamPatch, 1);
+        game.activity = Activity::FreeRoam;
+        let turn = game.turn;
+        game.apply(Action::UseItem(ItemType::RamPatch)).unwrap();
+        assert_eq!(game.turn, turn);
+    }
+
+    #[test]
+    fn a_flatline_costs_credits_intel_and_buffs() {
+        let mut game = Game::new(13);
+        game.player.credits = 101;
+        game.current_net_stats_mut().total_intel = 40.0;
+        game.player.buffs.add_buff(BuffType::Overclock, 3);
+        game.flatline(None);
+        // a quarter of the credits, rounded against the player
+        assert_eq!(game.player.credits, 75);
+        assert_eq!(game.current_intel(), 0.0);
+        assert!(game.player.buffs.has_buff(BuffType::Overclock).is_none());
+        assert!(game.apply(Action::Respawn).is_ok());
+        assert!(matches!(game.activity, Activity::FreeRoam));
+    }
+
+    #[test]
+    fn a_flatline_ends_an_ironman_run() {
+        let settings = GameSettings {
+            ironman: true,
+            ..GameSettings::default()
+        };
+        let mut game = Game::with_settings(13, settings);
+        game.player.credits = 100;
+        game.current_net_stats_mut().total_intel = 40.0;
+        game.flatline(None);
+        assert_eq!(game.player.credits, 100);
+        assert_eq!(game.current_intel(), 40.0);
+        assert_eq!(game.apply(Action::Respawn), Err(ActionError::RunOver));
+    }
+
+    #[test]
+    fn allocations_must_add_points() {
+        let mut game = Game::new(17);
+        let negative = Skills {
+            hacking: 3,
+            security: -1,
+        };
+        assert_eq!(
+            game.apply(Action::AllocateSkills(negative)),
+            Err(ActionError::InvalidAllocation)
+        );
+        assert_eq!(
+            game.apply(Action::AllocateSkills(Skills::empty())),
+            Err(ActionError::InvalidAllocation)
+        );
+        assert_eq!(game.player.skills, Skills::default());
+    }
+
+    #[test]
+    fn a_respec_refunds_down_to_the_floors_for_a_price() {
+        let mut game = Game::new(17);
+        game.activity = Activity::Interacting(InteractionType::BasicShop);
+        // 2 over the hacking floor, 3 over the security floor
+        let refunded = 5;
+        let cost = refunded * RESPEC_COST_PER_POINT;
+        game.player.credits = cost - 1;
+        assert_eq!(
+            game.apply(Action::Respec),
+            Err(ActionError::NotEnoughCredits { needed: cost })
+        );
+        game.player.credits = cost;
+        let outcomes = game.apply(Action::Respec).unwrap();
+        assert!(outcomes.contains(&Outcome::Respecced { refunded, cost }));
+        assert_eq!(game.player.credits, 0);
+        assert_eq!(game.player.skills.get(&SkillType::Hacking), 2);
+        assert_eq!(game.player.skills.get(&SkillType::Security), 1);
+        assert_eq!(
+            game.apply(Action::Respec),
+            Err(ActionError::NothingToRespec)
+        );
+    }
+
+    /// everything a contact of `level` dropped over `rolls` kills
+    fn loot_from(level: i32, rolls: usize) -> Vec<Loot> {
+        let mut game = Game::new(19);
+        let contact = Contact::new(level, &Networks::Internet, &mut game.rng);
+        for _ in 0..rolls {
+            game.roll_loot(&contact);
+        }
+        game.outcomes
+            .into_iter()
+            .filter_map(|outcome| match outcome {
+                Outcome::LootDropped { loot, .. } => Some(loot),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn stronger_contacts_drop_more_and_bigger_loot() {
+        let weak = loot_from(1, 200);
+        let strong = loot_from(8, 200);
+        assert!(strong.len() > weak.len());
+        for (loot, per_credit_drop) in [(&weak, 6), (&strong, 48)] {
+            for drop in loot {
+                if let Loot::Credits(amount) = drop {
+                    assert_eq!(*amount, per_credit_drop);
+                }
+            }
+            // blueprints only ever drop once
+            let blueprints = loot
+                .iter()
+                .filter(|drop| matches!(drop, Loot::Blueprint(_)))
+                .count();
+            assert!(blueprints <= 1);
+        }
+    }
+
+    /// damage dealt to each contact by the last hack
+    fn damage_dealt(game: &mut Game) -> Vec<i32> {
+        std::mem::take(&mut game.outcomes)
+            .into_iter()
+            .filter_map(|outcome| match outcome {
+                Outcome::DamageDealt { amount, .. } => Some(amount),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn a_sweep_splits_its_damage_across_every_contact() {
+        let mut game = Game::new(23);
+        game.player.skills.hacking = 10;
+        let contacts = (0..3)
+            .map(|_| {
+                let mut contact = Contact::new(1, &Networks::Internet, &mut game.rng);
+                contact.skills.security = 0;
+                contact
+            })
+            .collect();
+        game.activity = Activity::Combat(contacts);
+        // a full hit lands between 20 and 39
+        game.hack_contacts(Some(1), 1.0);
+        let single = damage_dealt(&mut game);
+        assert_eq!(single.len(), 1);
+        assert!(single[0] >= 20);
+
+        game.hack_contacts(None, 1.0);
+        let swept = damage_dealt(&mut game);
+        assert_eq!(swept.len(), 3);
+        assert!(swept.iter().all(|amount| *amount <= 13));
+    }
+
+    #[test]
+    fn every_level_on_the_curve_grants_skill_points() {
+        let mut game = Game::new(29);
+        assert_eq!(game.player.total_skill_points(), BASE_SKILL_POINTS);
+        game.gain_xp(xp_for_level(2) - 1);
+        assert_eq!(game.player.level(), 1);
+        assert!(game.outcomes.is_empty());
+
+        // one lump of xp can carry the player over several levels
+        game.gain_xp(xp_for_level(4) - xp_for_level(2) + 1);
+        assert_eq!(game.player.level(), 4);
+        let level_ups: Vec<_> = game
+            .outcomes
+            .iter()
+            .filter(|outcome| matches!(outcome, Outcome::LevelUp { .. }))
+            .collect();
+        assert_eq!(level_ups.len(), 3);
+        assert_eq!(
+            level_ups[1],
+            &Outcome::LevelUp {
+                level: 3,
+                skill_points: SKILL_POINTS_PER_LEVEL,
+                perk_available: true,
+            }
+        );
+        assert_eq!(
+            game.player.total_skill_points(),
+            BASE_SKILL_POINTS + 3 * SKILL_POINTS_PER_LEVEL
+        );
+    }
+
+    #[test]
+    fn upgrades_wait_for_their_prerequisites_and_stop_at_max_level() {
+        let mut game = Game::new(31);
+        game.activity = Activity::Interacting(InteractionType::BasicShop);
+        game.current_net_stats_mut().total_intel = 1000.0;
+        game.player.credits = 10_000;
+        let security = Action::BuyUpgrade(PlayerUpgradeType::SecurityUp);
+        let hp = Action::BuyUpgrade(PlayerUpgradeType::HPMaxUp);
+        assert_eq!(
+            game.apply(security.clone()),
+            Err(ActionError::UpgradeUnavailable)
+        );
+        game.apply(hp.clone()).unwrap();
+        game.apply(security).unwrap();
+
+        let max_level = upgrade_def(&PlayerUpgradeType::HPMaxUp).max_level;
+        for _ in 1..max_level {
+            game.apply(hp.clone()).unwrap();
+        }
+        let credits = game.player.credits;
+        assert_eq!(game.apply(hp), Err(ActionError::UpgradeMaxed));
+        assert_eq!(game.player.credits, credits);
+    }
+}
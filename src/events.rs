@@ -0,0 +1,60 @@
+use crate::engine::Tasks;
+use crate::items::ItemType;
+use crate::pieces::Networks;
+use crate::player::PlayerUpgradeType;
+
+/// Something that happened during a run; stats, intel and quests all keep track through these
+#[derive(Clone, Debug, PartialEq)]
+pub enum GameEvent {
+    ContactDefeated {
+        net: Networks,
+        level: i32,
+    },
+    TaskSucceeded {
+        task: Tasks,
+        net: Networks,
+    },
+    /// paid out for a task, loot or a deal, sent as it happens
+    CreditsEarned(i32),
+    /// paid for something in a shop or a conversation
+    CreditsSpent(i32),
+    NetworkChanged(Networks),
+    UpgradeBought(PlayerUpgradeType),
+    ItemBought(ItemType),
+    ItemUsed(ItemType),
+    ShopVisited,
+    /// by conversation id
+    ConversationFinished(String),
+    /// where the player stands on a network, sent after every action
+    IntelLevel {
+        net: Networks,
+        level: i32,
+    },
+    /// got away from a fight
+    Escaped(Networks),
+    Flatlined {
+        net: Networks,
+        /// the run's settings wipe what the player knew about the network
+        reset_intel: bool,
+    },
+}
+
+impl GameEvent {
+    /// the network the event happened on, if it belongs to one
+    pub fn network(&self) -> Option<&Networks> {
+        match self {
+            GameEvent::ContactDefeated { net, .. }
+            | GameEvent::TaskSucceeded { net, .. }
+            | GameEvent::NetworkChanged(net)
+            | GameEvent::IntelLevel { net, .. }
+            | GameEvent::Escaped(net)
+            | GameEvent::Flatlined { net, .. } => Some(net),
+            _ => None,
+        }
+    }
+}
+
+/// Anything that keeps a tally of what happens in a run
+pub trait EventListener {
+    fn on_event(&mut self, event: &GameEvent);
+}
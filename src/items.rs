@@ -0,0 +1,111 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// RAM restored by a RAM patch
+pub const RAM_PATCH_AMOUNT: i32 = 30;
+/// HP restored by a repair kit
+pub const REPAIR_KIT_AMOUNT: i32 = 40;
+/// damage an exploit deals to every contact, ignoring skills
+pub const EXPLOIT_DAMAGE: i32 = 25;
+
+/// Consumables the player can carry, buy and find on contacts
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum ItemType {
+    RamPatch,
+    RepairKit,
+    Exploit,
+    Smoke,
+}
+
+impl ItemType {
+    pub const ALL: [ItemType; 4] = [
+        ItemType::RamPatch,
+        ItemType::RepairKit,
+        ItemType::Exploit,
+        ItemType::Smoke,
+    ];
+
+    pub fn name(&self) -> String {
+        match *self {
+            ItemType::RamPatch => "RAM Patch".to_string(),
+            ItemType::RepairKit => "Repair Kit".to_string(),
+            ItemType::Exploit => "Zero-day Exploit".to_string(),
+            ItemType::Smoke => "Smoke Program".to_string(),
+        }
+    }
+
+    pub fn description(&self) -> String {
+        match *self {
+            ItemType::RamPatch => format!("Restores {} RAM", RAM_PATCH_AMOUNT),
+            ItemType::RepairKit => format!("Restores {} HP", REPAIR_KIT_AMOUNT),
+            ItemType::Exploit => format!(
+                "Deals {} damage to every contact, no RAM needed",
+                EXPLOIT_DAMAGE
+            ),
+            ItemType::Smoke => "Escape from combat, guaranteed".to_string(),
+        }
+    }
+
+    /// shop price in credits
+    pub fn price(&self) -> i32 {
+        match *self {
+            ItemType::RamPatch => 40,
+            ItemType::RepairKit => 50,
+            ItemType::Exploit => 90,
+            ItemType::Smoke => 60,
+        }
+    }
+
+    /// rarer stock only shows up once the player knows the shop's network
+    pub fn min_intel_level(&self) -> i32 {
+        match *self {
+            ItemType::RamPatch | ItemType::RepairKit => 0,
+            ItemType::Smoke => 1,
+            ItemType::Exploit => 2,
+        }
+    }
+
+    /// only makes sense with a contact to use it on
+    pub fn combat_only(&self) -> bool {
+        matches!(*self, ItemType::Exploit | ItemType::Smoke)
+    }
+}
+
+/// Stacks of consumables, kept in a fixed order so the UI doesn't shuffle
+#[derive(Default, Serialize, Deserialize)]
+pub struct Inventory {
+    items: BTreeMap<ItemType, u32>,
+}
+
+impl Inventory {
+    pub fn count(&self, item: ItemType) -> u32 {
+        self.items.get(&item).copied().unwrap_or(0)
+    }
+
+    pub fn add(&mut self, item: ItemType, amount: u32) {
+        *self.items.entry(item).or_insert(0) += amount;
+    }
+
+    /// take one of `item` out, false if there wasn't one
+    pub fn take(&mut self, item: ItemType) -> bool {
+        match self.items.get_mut(&item) {
+            Some(count) if *count > 0 => {
+                *count -= 1;
+                if *count == 0 {
+                    self.items.remove(&item);
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// every item held, with how many of it
+    pub fn iter(&self) -> impl Iterator<Item = (ItemType, u32)> + '_ {
+        self.items.iter().map(|(item, count)| (*item, *count))
+    }
+}
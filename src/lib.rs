@@ -0,0 +1,18 @@
+pub mod abilities;
+pub mod behaviour;
+pub mod buffs;
+pub mod conversation;
+pub mod dialogue;
+pub mod encounters;
+pub mod engine;
+pub mod events;
+pub mod items;
+pub mod loot;
+pub mod pieces;
+pub mod player;
+pub mod quests;
+pub mod records;
+pub mod replay;
+pub mod save;
+pub mod upgrades;
+pub mod utils;
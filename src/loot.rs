@@ -0,0 +1,182 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::items::ItemType;
+use crate::pieces::Networks;
+
+/// Rare programs that can only be found on defeated contacts, each one only once
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Blueprint {
+    IceBreaker,
+    GhostShell,
+    BlackIce,
+}
+
+impl Blueprint {
+    pub fn name(&self) -> String {
+        match *self {
+            Blueprint::IceBreaker => "IceBreaker".to_string(),
+            Blueprint::GhostShell => "GhostShell".to_string(),
+            Blueprint::BlackIce => "Black ICE".to_string(),
+        }
+    }
+
+    pub fn description(&self) -> String {
+        match *self {
+            Blueprint::IceBreaker => {
+                "Source for a program that chews through firewalls".to_string()
+            }
+            Blueprint::GhostShell => "Source for a program that hides you from tracers".to_string(),
+            Blueprint::BlackIce => "Source for a lethal countermeasure, military grade".to_string(),
+        }
+    }
+}
+
+/// What a loot table entry can drop
+pub enum LootDrop {
+    /// credits per point of drop quality
+    Credits(i32),
+    Item(ItemType),
+    /// data fragments per point of drop quality
+    DataFragments(i32),
+    Blueprint(Blueprint),
+}
+
+pub struct LootEntry {
+    pub drop: LootDrop,
+    /// chance to drop from a quality 1 contact
+    pub chance: f32,
+    /// extra chance for every point of quality above 1
+    pub chance_per_quality: f32,
+}
+
+impl LootEntry {
+    pub fn chance(&self, quality: f32) -> f32 {
+        (self.chance + self.chance_per_quality * (quality - 1.0).max(0.0)).min(1.0)
+    }
+}
+
+/// Something a defeated contact actually dropped
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Loot {
+    Credits(i32),
+    Item(ItemType),
+    DataFragments(i32),
+    Blueprint(Blueprint),
+}
+
+impl Loot {
+    /// roll the amount for an entry that just dropped
+    pub fn from_drop(drop: &LootDrop, quality: f32) -> Self {
+        match *drop {
+            LootDrop::Credits(per_quality) => {
+                Loot::Credits((per_quality as f32 * quality).ceil() as i32)
+            }
+            LootDrop::Item(item) => Loot::Item(item),
+            LootDrop::DataFragments(per_quality) => {
+                Loot::DataFragments((per_quality as f32 * quality).ceil() as i32)
+            }
+            LootDrop::Blueprint(blueprint) => Loot::Blueprint(blueprint),
+        }
+    }
+}
+
+impl fmt::Display for Loot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Loot::Credits(amount) => write!(f, "{} credits", amount),
+            Loot::Item(item) => write!(f, "a {}", item.name()),
+            Loot::DataFragments(amount) => write!(f, "{} data fragments", amount),
+            Loot::Blueprint(blueprint) => write!(f, "the {} blueprint", blueprint.name()),
+        }
+    }
+}
+
+/// how good the loot on a contact is, harder networks and stronger contacts carry better stuff
+pub fn drop_quality(level: i32, net: &Networks) -> f32 {
+    level as f32 * net.difficulty()
+}
+
+const INTERNET_LOOT: &[LootEntry] = &[
+    LootEntry {
+        drop: LootDrop::Credits(6),
+        chance: 0.6,
+        chance_per_quality: 0.05,
+    },
+    LootEntry {
+        drop: LootDrop::DataFragments(1),
+        chance: 0.3,
+        chance_per_quality: 0.05,
+    },
+    LootEntry {
+        drop: LootDrop::Item(ItemType::RamPatch),
+        chance: 0.12,
+        chance_per_quality: 0.02,
+    },
+    LootEntry {
+        drop: LootDrop::Item(ItemType::RepairKit),
+        chance: 0.12,
+        chance_per_quality: 0.02,
+    },
+    LootEntry {
+        drop: LootDrop::Item(ItemType::Smoke),
+        chance: 0.06,
+        chance_per_quality: 0.01,
+    },
+    LootEntry {
+        drop: LootDrop::Item(ItemType::Exploit),
+        chance: 0.04,
+        chance_per_quality: 0.01,
+    },
+    LootEntry {
+        drop: LootDrop::Blueprint(Blueprint::IceBreaker),
+        chance: 0.01,
+        chance_per_quality: 0.005,
+    },
+];
+
+const SIPRNET_LOOT: &[LootEntry] = &[
+    LootEntry {
+        drop: LootDrop::Credits(8),
+        chance: 0.7,
+        chance_per_quality: 0.03,
+    },
+    LootEntry {
+        drop: LootDrop::DataFragments(2),
+        chance: 0.5,
+        chance_per_quality: 0.03,
+    },
+    LootEntry {
+        drop: LootDrop::Item(ItemType::RepairKit),
+        chance: 0.15,
+        chance_per_quality: 0.01,
+    },
+    LootEntry {
+        drop: LootDrop::Item(ItemType::Exploit),
+        chance: 0.1,
+        chance_per_quality: 0.01,
+    },
+    LootEntry {
+        drop: LootDrop::Item(ItemType::Smoke),
+        chance: 0.08,
+        chance_per_quality: 0.01,
+    },
+    LootEntry {
+        drop: LootDrop::Blueprint(Blueprint::GhostShell),
+        chance: 0.02,
+        chance_per_quality: 0.005,
+    },
+    LootEntry {
+        drop: LootDrop::Blueprint(Blueprint::BlackIce),
+        chance: 0.005,
+        chance_per_quality: 0.0025,
+    },
+];
+
+/// every entry is rolled on its own, so one kill can drop several things or nothing
+pub fn loot_table(net: &Networks) -> &'static [LootEntry] {
+    match net {
+        Networks::Internet => INTERNET_LOOT,
+        Networks::SIPRnet => SIPRNET_LOOT,
+    }
+}
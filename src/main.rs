@@ -1,28 +1,59 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")] // hide console window on Windows in release
 
-use conversation::Conversation;
 use eframe::egui;
 use egui::{Color32, RichText};
-use quests::QuestID;
-use rand::{thread_rng, Rng};
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::time::{Duration, Instant};
 
-mod buffs;
-mod conversation;
-mod pieces;
-mod player;
-mod quests;
-mod utils;
-
-use pieces::{Contact, Networks};
-use player::{NetStats, Player, PlayerFlag, PlayerUpgradeType};
-use utils::roll_encounter;
+use netrunner::abilities::ability_def;
+use netrunner::behaviour::ContactAbility;
+use netrunner::buffs::BuffType;
+use netrunner::conversation::DialogueEffect;
+use netrunner::dialogue::{self, DialogueLibrary};
+use netrunner::encounters::ENCOUNTERS;
+use netrunner::engine::{
+    Action, Activity, Game, GameSettings, InteractionType, Outcome, Tasks, RESPEC_COST_PER_POINT,
+};
+use netrunner::items::ItemType;
+use netrunner::pieces::{Networks, SkillType, Skills};
+use netrunner::player::{Perk, PlayerFlag};
+use netrunner::quests::{self, Objective};
+use netrunner::records::{self, RunRecord};
+use netrunner::replay::{self, Replayer};
+use netrunner::save;
+use netrunner::upgrades::CATALOG;
+use netrunner::utils;
 
 // update at this framerate when there is no user input
 const MAX_WAIT_BETWEEN_FRAMES: Duration = Duration::from_millis(200); // 200ms = 5 fps
 
 fn main() -> Result<(), eframe::Error> {
     // env_logger::init(); // Log to stderr (if you run with `RUST_LOG=debug`).
+    let args = parse_args();
+    if args.check_content {
+        check_content();
+    }
+    let replayer = match &args.replay {
+        Some(path) => match replay::load_replay(path) {
+            Ok(replay) => Some(Replayer::new(replay)),
+            Err(err) => {
+                eprintln!("Could not load replay: {}", err);
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+    if args.headless {
+        match replayer {
+            Some(replayer) => run_headless_replay(replayer),
+            None => {
+                eprintln!("--headless needs a --replay <file> to play");
+                std::process::exit(1);
+            }
+        }
+        return Ok(());
+    }
     let options = eframe::NativeOptions {
         initial_window_size: Some(egui::vec2(450.0, 400.0)),
         always_on_top: true,
@@ -31,327 +62,391 @@ fn main() -> Result<(), eframe::Error> {
     eframe::run_native(
         "cybergame",
         options,
-        Box::new(|_cc| Box::<NetrunnerGame>::default()),
+        Box::new(move |_cc| match (replayer, args.seed) {
+            (Some(replayer), _) => Box::new(NetrunnerGame::with_replay(replayer)),
+            (None, Some(seed)) => Box::new(NetrunnerGame::with_settings(seed, args.settings)),
+            (None, None) if args.new_run => {
+                Box::new(NetrunnerGame::with_settings(rand::random(), args.settings))
+            }
+            (None, None) => Box::new(NetrunnerGame::load_or_default()),
+        }),
     )
 }
 
-#[derive(PartialEq)]
-enum Tasks {
-    Search,
-    Datamine,
-    Recover,
+/// command line options
+#[derive(Default)]
+struct Args {
+    /// `--seed <n>` starts a fresh, reproducible run instead of resuming the save
+    seed: Option<u64>,
+    /// `--replay <file>` plays a recorded run back step by step
+    replay: Option<PathBuf>,
+    /// `--headless` prints the replay to stdout instead of opening a window
+    headless: bool,
+    /// `--check-content` validates the content files and exits
+    check_content: bool,
+    /// `--ironman` and `--credit-penalty <0.0-1.0>` pick how harsh flatlining is,
+    /// `--claim-rewards` leaves quest rewards in the quest log until claimed
+    settings: GameSettings,
+    /// any run setting was given, so start a fresh run instead of resuming the save
+    new_run: bool,
 }
 
-impl Tasks {
-    fn description(&self) -> RichText {
-        match *self {
-            Tasks::Search => RichText::new("+ Credits, ???").color(Color32::DARK_GRAY),
-            Tasks::Datamine => RichText::new("High risk, ++ Credits").color(Color32::DARK_GRAY),
-            Tasks::Recover => RichText::new("Low risk, ++ RAM").color(Color32::DARK_GRAY),
+fn parse_args() -> Args {
+    let mut parsed = Args::default();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--seed" => match args.next().and_then(|seed| seed.parse().ok()) {
+                Some(seed) => parsed.seed = Some(seed),
+                None => {
+                    eprintln!("--seed needs a whole number, like --seed 42");
+                    std::process::exit(1);
+                }
+            },
+            "--replay" => match args.next() {
+                Some(path) => parsed.replay = Some(PathBuf::from(path)),
+                None => {
+                    eprintln!("--replay needs a file to play");
+                    std::process::exit(1);
+                }
+            },
+            "--headless" => parsed.headless = true,
+            "--check-content" => parsed.check_content = true,
+            "--ironman" => {
+                parsed.settings.ironman = true;
+                parsed.new_run = true;
+            }
+            "--claim-rewards" => {
+                parsed.settings.manual_quest_rewards = true;
+                parsed.new_run = true;
+            }
+            "--credit-penalty" => {
+                if let Some(penalty) = args.next().and_then(|penalty| penalty.parse().ok()) {
+                    parsed.settings.credit_penalty = penalty;
+                    parsed.new_run = true;
+                }
+            }
+            _ => eprintln!("Ignoring unknown argument '{}'", arg),
         }
     }
+    parsed
 }
 
-#[derive(Clone, Copy, PartialEq)]
-enum InteractionType {
-    BasicShop,
+/// report every problem in the content files, exits non-zero if there are any
+fn check_content() -> ! {
+    let (library, errors) = DialogueLibrary::load_dir(&dialogue::dialogue_dir());
+    let mut problems = errors.len();
+    for err in errors.iter() {
+        eprintln!("{}", err);
+    }
+    for encounter in ENCOUNTERS.iter() {
+        if library.get(encounter.conversation).is_none() {
+            eprintln!(
+                "encounter '{}' needs conversation '{}', which didn't load",
+                encounter.name, encounter.conversation
+            );
+            problems += 1;
+        }
+    }
+    // the file as the writers left it, not the copy built into this binary
+    let quests_path = utils::content_path(quests::QUESTS_PATH);
+    let quests = match fs::read_to_string(&quests_path) {
+        Ok(text) => {
+            if text != quests::BUILT_IN_QUESTS {
+                println!(
+                    "{} has changed since this build, rebuild to play with it",
+                    quests_path.display()
+                );
+            }
+            quests::parse_quests(&text).map_err(|err| err.to_string())
+        }
+        Err(err) => Err(err.to_string()),
+    };
+    let quests = match quests {
+        Ok(quests) => quests,
+        Err(err) => {
+            eprintln!("{}: {}", quests_path.display(), err);
+            problems += 1;
+            vec![]
+        }
+    };
+    for problem in quests::validate_quests(&quests) {
+        eprintln!("{}: {}", quests_path.display(), problem);
+        problems += 1;
+    }
+    for quest in quests.iter() {
+        for objective in quest.objectives.iter() {
+            if let Objective::FinishConversation(id) = objective {
+                if library.get(id).is_none() {
+                    eprintln!(
+                        "quest '{}' waits on conversation '{}', which didn't load",
+                        quest.id, id
+                    );
+                    problems += 1;
+                }
+            }
+        }
+    }
+    for convo in library.iter() {
+        for node in convo.nodes.values() {
+            for effect in node.effects.iter() {
+                let (DialogueEffect::StartQuest(id) | DialogueEffect::AdvanceQuest(id)) = effect
+                else {
+                    continue;
+                };
+                if !quests.iter().any(|quest| &quest.id == id) {
+                    eprintln!(
+                        "conversation '{}' refers to missing quest '{}'",
+                        convo.id, id
+                    );
+                    problems += 1;
+                }
+            }
+        }
+    }
+    println!(
+        "{} conversation(s), {} quest(s) OK, {} problem(s)",
+        library.len(),
+        quests.len(),
+        problems
+    );
+    std::process::exit(if problems == 0 { 0 } else { 1 });
 }
 
-/// An activity describes what the player is currently doing
-enum Activity {
-    FreeRoam,
-    Combat(Vec<Contact>),
-    Interacting(InteractionType),
-    Conversing(Conversation),
+/// play a whole replay without a window, printing what the terminal would show
+fn run_headless_replay(mut replayer: Replayer) {
+    let mut game = replayer.new_game();
+    let (library, errors) = DialogueLibrary::load_dir(&dialogue::dialogue_dir());
+    for err in errors {
+        eprintln!("Content problem: {}", err);
+    }
+    game.set_dialogue(library);
+    while let Some(result) = replayer.step(&mut game) {
+        match result {
+            Ok(outcomes) => {
+                for outcome in outcomes {
+                    println!("[turn {}] {}", game.turn, outcome);
+                }
+            }
+            Err(err) => {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
+        }
+    }
+    println!(
+        "Replay finished on turn {}: {} HP, {} RAM, {} credits, {} XP",
+        game.turn, game.player.hp.value, game.player.ram.value, game.player.credits, game.player.xp
+    );
+}
+
+fn task_description(task: &Tasks) -> RichText {
+    RichText::new(task.description()).color(Color32::DARK_GRAY)
 }
 
+/// The egui front end; all game rules live in `Game`
 struct NetrunnerGame {
-    // game objects
-    player: Player,
+    game: Game,
     terminal_lines: Vec<String>,
-    // enums
-    activity: Activity,
-    current_net: Networks,
     current_task: Tasks,
-    // values
-    turn: i32,
     last_frame_time: Instant,
+    /// set while watching a replay, player input is disabled
+    replayer: Option<Replayer>,
+    /// skill points the player is about to commit
+    pending_skills: Skills,
+    show_quest_log: bool,
+    /// index of the contact hacks are aimed at
+    selected_target: usize,
+    /// off when an unreadable save couldn't be moved aside, so quitting doesn't clobber it
+    autosave: bool,
+    /// best runs first, read once the flatline screen needs them
+    past_runs: Option<Result<Vec<RunRecord>, String>>,
+}
+
+fn welcome_lines() -> Vec<String> {
+    vec![
+        "welcome to cybergame".to_string(),
+        "strap in, choomba".to_string(),
+    ]
 }
 
 impl Default for NetrunnerGame {
     fn default() -> Self {
-        Self {
-            player: Player::default(),
-            terminal_lines: vec![
-                "welcome to cybergame".to_string(),
-                "strap in, choomba".to_string(),
-            ],
-            activity: Activity::FreeRoam,
-            current_net: Networks::Internet,
-            current_task: Tasks::Datamine,
-            turn: 1,
-            last_frame_time: Instant::now(),
-        }
+        Self::with_game(Game::default(), welcome_lines())
     }
 }
 
 impl NetrunnerGame {
-    fn do_turn(&mut self) {
-        self.turn += 1;
-        self.player.buffs.do_turn();
-    }
-
-    fn combat_attack(&mut self) {
-        let mut dead_hostiles = vec![];
-        let mut print_lines = vec![];
-        if let Activity::Combat(contacts) = &mut self.activity {
-            // we're in combat! iterate over each foe
-            for (index, contact) in contacts.iter_mut().enumerate() {
-                // calculate dmg to hostile
-                let min_dmg_to_hostile =
-                    ((2 * self.player.skills.hacking) - contact.skills.security).max(0);
-                let max_dmg_to_hostile =
-                    ((4 * self.player.skills.hacking) - (contact.skills.security / 2)).max(1);
-                let dmg_to_hostile =
-                    rand::thread_rng().gen_range(min_dmg_to_hostile..max_dmg_to_hostile);
-                // buff dmg
-                let buff_dmg = self.player.buffs.get_buff_dmg(dmg_to_hostile);
-                let buff_text = if buff_dmg > 0 {
-                    format!(" + {}", buff_dmg)
-                } else {
-                    String::new()
-                };
+    /// wrap a game, loading the content files it needs
+    fn with_game(game: Game, terminal_lines: Vec<String>) -> Self {
+        let mut frontend = Self {
+            game,
+            terminal_lines,
+            current_task: Tasks::Datamine,
+            last_frame_time: Instant::now(),
+            replayer: None,
+            pending_skills: Skills::empty(),
+            show_quest_log: false,
+            selected_target: 0,
+            autosave: true,
+            past_runs: None,
+        };
+        frontend.load_dialogue();
+        frontend
+    }
 
-                // when the contact's HP reaches zero death/xp messages are immediately displayed
-                // but the messages in print_lines aren't until the end of the loop
-                // this is a problem because I want "you deal X damage" to show up before "the bad guy dies"
-                // TODO: figure out how to resolve this
-                print_lines.push(format!(
-                    "You deal {}{} damage to {}.",
-                    dmg_to_hostile, buff_text, contact.name
-                ));
-                contact.hp.change_by(-(dmg_to_hostile + buff_dmg));
-
-                if contact.hp.value <= 0 {
-                    // killed an enemy!
-                    print_lines.push("You are victorious!".to_owned());
-                    dead_hostiles.push(index);
-                    self.player.stats.kills += 1;
-                    self.player.add_xp(contact.reward());
-                    self.player
-                        .net_stats
-                        .get_mut(&self.current_net)
-                        .unwrap()
-                        .total_intel += 12.0;
-                } else {
-                    // we didn't kill the enemy, it will do damage
-                    let min_dmg_to_player =
-                        (2 + contact.skills.hacking - self.player.skills.security).max(0);
-                    let max_dmg_to_player =
-                        (4 + contact.skills.hacking - (self.player.skills.security / 2)).max(1);
-                    let dmg_to_player =
-                        rand::thread_rng().gen_range(min_dmg_to_player..max_dmg_to_player);
-                    // apply damage to player
-                    self.player.hp.change_by(-dmg_to_player);
-                    print_lines.push(format!(
-                        "You take {} damage from {}.",
-                        dmg_to_player, contact.name
-                    ));
-                }
-            }
-            for dead_index in dead_hostiles.iter().rev() {
-                // remove dead contacts
-                contacts.remove(*dead_index);
-            }
-            if contacts.len() == 0 {
-                self.activity = Activity::FreeRoam;
-            }
-            // now we're done with "contacts", can mutably borrow again
-            for _ in 0..dead_hostiles.len() {
-                self.trigger_quest(&QuestID::CombatVictory);
-            }
-            for line in &print_lines {
-                self.terminal_print(line);
-            }
-        } else {
-            panic!("combat_attack() called not in combat")
+    fn load_dialogue(&mut self) {
+        let (library, errors) = DialogueLibrary::load_dir(&dialogue::dialogue_dir());
+        for err in errors {
+            self.terminal_print(format!("Content problem: {}", err).as_str());
+        }
+        if library.is_empty() {
+            self.terminal_print(
+                "No conversations loaded: NPCs won't turn up and SIPRnet stays locked.",
+            );
         }
+        self.game.set_dialogue(library);
     }
 
-    fn trigger_quest(&mut self, quest_id: &QuestID) {
-        if let Some(quest) = self.player.quests.get_mut(quest_id) {
-            if quest.is_finished() {
-                return
-            }
-            quest.increment();
-            if quest.is_finished() {
-                // these things only happen if this increment just caused us to complete the quest
-                match quest.reward {
-                    quests::QuestReward::XP(amnt) => {
-                        self.player.add_xp(amnt);
-                        self.terminal_print(format!("You gained {} XP.", amnt).as_str());
-                    }
-                }
-                match quest_id {
-                    QuestID::CombatVictory => {
-                        self.terminal_print("Victorious in combat, you finish a quest!");
-                    }
-                    QuestID::DatamineSuccess => {
-                        self.terminal_print("Successful in datamining, you finish a quest!");
-                    }
-                }
-            }
-        }
+    fn with_settings(seed: u64, settings: GameSettings) -> Self {
+        Self::with_game(Game::with_settings(seed, settings), welcome_lines())
     }
 
-    fn ability_overclock(&mut self) {
-        self.terminal_print("You overclock your systems, empowering your next attack.");
-        self.player.buffs.add_buff(buffs::BuffType::Overclock, 1);
+    /// after an ironman run ends, start over with the same settings
+    fn new_run(&mut self) {
+        self.game = Game::with_settings(rand::random(), self.game.settings.clone());
+        self.load_dialogue();
+        self.pending_skills = Skills::empty();
+        self.past_runs = None;
+        self.terminal_print("A new run begins.");
     }
 
-    fn do_task(&mut self) {
-        let difficulty = self.current_net.difficulty();
+    fn with_replay(replayer: Replayer) -> Self {
+        let mut frontend = Self::with_game(replayer.new_game(), welcome_lines());
+        frontend.replayer = Some(replayer);
+        frontend.terminal_print("Watching a replay.");
+        frontend
+    }
 
-        match self.current_task {
-            Tasks::Search => {
-                self.do_task_search(difficulty);
+    /// resume from the save file if there is one, otherwise start a new run
+    fn load_or_default() -> Self {
+        let path = Path::new(save::SAVE_PATH);
+        if !path.exists() {
+            return Self::default();
+        }
+        match save::load_game(path) {
+            Ok((game, terminal_lines)) => {
+                let mut frontend = Self::with_game(game, terminal_lines);
+                frontend.terminal_print("Save loaded, welcome back.");
+                frontend
             }
-            Tasks::Datamine => {
-                self.do_task_datamine(difficulty);
+            Err(err) => {
+                let mut frontend = Self::default();
+                frontend.terminal_print(format!("Could not load save: {}", err).as_str());
+                match save::set_aside(path) {
+                    Ok(backup) => frontend.terminal_print(
+                        format!("The old save was moved to {}.", backup.display()).as_str(),
+                    ),
+                    Err(err) => {
+                        frontend.autosave = false;
+                        frontend.terminal_print(
+                            format!(
+                                "Could not move the old save aside ({}), it won't be autosaved over.",
+                                err
+                            )
+                            .as_str(),
+                        );
+                    }
+                }
+                frontend
             }
-            Tasks::Recover => self.do_task_recovery(), // Tasks::Social => self.terminal_print("Nah, you don't want to do that."),
         }
     }
 
-    fn go_shopping(&mut self) {
-        // gracefully transition the player into the shopping state
-
-        // set game state
-        self.activity = Activity::Interacting(InteractionType::BasicShop);
-        // text in terminal
-        let net_name = match self.current_net {
-            Networks::Internet => "the internet",
-            Networks::SIPRnet => "SIPRnet",
-        };
-        self.terminal_print(
-            format!("You see what's available for purchase on {}.", { net_name }).as_str(),
-        );
-    }
-
-    fn do_task_recovery(&mut self) {
-        let is_max_ram = self.player.ram.upper_limit == self.player.ram.value;
-        let is_max_hp = self.player.hp.upper_limit == self.player.hp.value;
-        if (is_max_hp) && (is_max_ram) {
-            self.terminal_print("You are already at maximum HP and RAM");
-            return;
-        }
-
-        self.do_turn();
-
-        // Recover RAM
-        if !is_max_ram {
-            let reward = rand::thread_rng().gen_range(7..22);
-            self.player.ram.change_by(reward);
-            self.terminal_print(format!("You recover {} RAM", reward).as_str());
+    fn save(&mut self) {
+        match save::save_game(&self.game, &self.terminal_lines, Path::new(save::SAVE_PATH)) {
+            Ok(()) => self.terminal_print("Game saved."),
+            Err(err) => self.terminal_print(format!("Could not save: {}", err).as_str()),
         }
+    }
 
-        // Recover HP
-        if !is_max_hp {
-            let reward = rand::thread_rng().gen_range(5..16);
-            self.player.hp.change_by(reward);
-            self.terminal_print(format!("You recover {} HP", reward).as_str());
+    fn export_replay(&mut self) {
+        match replay::save_replay(&self.game.replay(), Path::new(replay::REPLAY_PATH)) {
+            Ok(()) => {
+                self.terminal_print(format!("Replay exported to {}.", replay::REPLAY_PATH).as_str())
+            }
+            Err(err) => self.terminal_print(format!("Could not export replay: {}", err).as_str()),
         }
     }
 
-    fn do_task_datamine(&mut self, difficulty: f32) {
-        self.do_turn();
-        let mut rng = thread_rng();
-        let success_chance = 0.6;
-        let roll_success: f32 = rng.gen();
-        if roll_encounter(1.0 - success_chance) {
-            // success - earn credits
-            self.player.stats.datamine_success += 1;
-            let reward_amount: i32 = (roll_success * difficulty * 14.5).ceil() as i32;
-            self.player.credits += reward_amount;
-            self.player
-                .net_stats
-                .get_mut(&self.current_net)
-                .unwrap()
-                .total_intel += 3.0;
-            self.terminal_print(
-                format!(
-                    "({:.1}) You found some interesting data worth {} credits",
-                    success_chance, reward_amount
-                )
-                .as_str(),
-            );
-            self.trigger_quest(&QuestID::DatamineSuccess);
-        } else {
-            // "fail" - combat
-            let new_contact = Contact::new(difficulty.ceil() as i32, &self.current_net);
-            let contact_name = new_contact.name.clone();
-            self.terminal_print(
-                format!(
-                    "({:.1}) You run into a nasty piece of malware - {}",
-                    { 1.0 - success_chance },
-                    contact_name
-                )
-                .as_str(),
-            );
-            self.activity = Activity::Combat(vec![new_contact]);
+    /// play the next recorded action, returns false once there is nothing left to play
+    fn replay_step(&mut self) -> bool {
+        let Some(replayer) = self.replayer.as_mut() else {
+            return false;
+        };
+        match replayer.step(&mut self.game) {
+            Some(Ok(outcomes)) => {
+                for outcome in outcomes {
+                    self.terminal_print(outcome.to_string().as_str());
+                }
+                true
+            }
+            Some(Err(err)) => {
+                self.terminal_print(err.to_string().as_str());
+                false
+            }
+            None => false,
         }
     }
 
-    fn do_task_search(&mut self, difficulty: f32) {
-        self.do_turn();
-        let mut rng = thread_rng();
-        let roll_success: f32 = rng.gen();
+    fn replay_controls(&mut self, ui: &mut egui::Ui) {
+        let Some(replayer) = self.replayer.as_ref() else {
+            return;
+        };
+        let (played, total) = replayer.progress();
+        let next_action = replayer
+            .peek()
+            .map(|logged| format!("next: turn {} {:?}", logged.turn, logged.action))
+            .unwrap_or_else(|| "replay finished".to_string());
+        ui.horizontal(|ui| {
+            ui.label(format!("Replay {}/{}", played, total));
+            if ui.button("Step").clicked() {
+                self.replay_step();
+            }
+            if ui.button("Play to end").clicked() {
+                while self.replay_step() {}
+            }
+            if ui.button("Take over").clicked() {
+                // stop watching and keep playing from here
+                self.replayer = None;
+                self.terminal_print("You take over the run.");
+            }
+        });
+        ui.label(RichText::new(next_action).weak());
+    }
 
-        // first-time encounters
-        match self.current_net {
-            Networks::Internet => {
-                if !self.player.has_flag(&PlayerFlag::DiscoveredShopBasic)
-                    && self.player.credits >= 100
-                {
-                    if roll_encounter(0.2) {
-                        // stumble across the shop
-                        self.terminal_print(
-                        "You stumble across some sort of virtual server for secure transations.",
-                    );
-                        self.go_shopping();
-                        return;
+    /// hand an action to the engine and print whatever came of it
+    fn act(&mut self, action: Action) {
+        match self.game.apply(action) {
+            Ok(outcomes) => {
+                for outcome in outcomes {
+                    self.terminal_print(outcome.to_string().as_str());
+                    if outcome == Outcome::RunEnded {
+                        self.record_run();
                     }
                 }
             }
-            Networks::SIPRnet => {}
+            Err(err) => self.terminal_print(err.to_string().as_str()),
         }
+    }
 
-        let success_chance = 0.8;
-        if roll_encounter(1.0 - success_chance) {
-            // good thing - search success
-            self.player.stats.search_success += 1;
-            let reward_amount: i32 = (roll_success * difficulty * 6.5).ceil() as i32;
-            self.player.credits += reward_amount;
-            self.terminal_print(
-                format!(
-                    "({:.1}) You found some particularly interesting data worth {} credits",
-                    success_chance, reward_amount
-                )
-                .as_str(),
-            );
-        } else {
-            // regen a bit of ram
-            let reward_amount: i32 = (roll_success * 7.5 + difficulty).ceil() as i32;
-            self.player.ram.change_by(reward_amount);
-            self.terminal_print(
-                format!(
-                    "({:.1}) You don't find anything new, but regenerate {} RAM",
-                    { 1.0 - success_chance },
-                    reward_amount
-                )
-                .as_str(),
-            );
+    fn record_run(&mut self) {
+        let path = Path::new(records::RECORDS_PATH);
+        if let Err(err) = records::record_run(self.game.run_record(), path) {
+            self.terminal_print(format!("Could not record the run: {}", err).as_str());
         }
+        // read again, this run included
+        self.past_runs = None;
     }
 
     fn terminal_print(&mut self, line: &str) {
@@ -359,33 +454,45 @@ impl NetrunnerGame {
     }
 
     fn player_stats_table(&mut self, ui: &mut egui::Ui) {
+        let player = &self.game.player;
         egui::Grid::new("some_unique_id").show(ui, |ui| {
             // only one row: hp and ram stats
-            ui.label(colored_label(
-                "HP",
-                self.player.hp.value,
-                self.player.hp.upper_limit,
-            ));
+            ui.label(colored_label("HP", player.hp.value, player.hp.upper_limit));
             ui.horizontal(|ui| {
                 ui.separator();
                 ui.label(colored_label(
                     "RAM",
-                    self.player.ram.value,
-                    self.player.ram.upper_limit,
+                    player.ram.value,
+                    player.ram.upper_limit,
                 ));
                 ui.separator();
-                ui.label(format!("Credits: {}", self.player.credits));
+                ui.label(format!("Credits: {}", player.credits));
+                if player.data_fragments > 0 {
+                    ui.separator();
+                    ui.label(format!("Data fragments: {}", player.data_fragments));
+                }
             });
             ui.end_row();
+            // second row: level and xp towards the next one
+            let (xp_into_level, xp_for_level) = player.level_progress();
+            ui.label(format!("Level {}", player.level()));
+            ui.add(
+                egui::ProgressBar::new(xp_into_level as f32 / xp_for_level as f32)
+                    .text(format!("{} / {} XP", xp_into_level, xp_for_level)),
+            );
+            ui.end_row();
         });
     }
 
     fn collapsible_stats_table(&mut self, ui: &mut egui::Ui) {
-        let pts = self.player.available_skill_points();
+        let pts = self.game.player.available_skill_points();
         let id = ui.make_persistent_id("collapsible_stats_table");
         egui::collapsing_header::CollapsingState::load_with_default_open(ui.ctx(), id, false)
             .show_header(ui, |ui| {
                 ui.label("Your Stats");
+                if self.game.player.available_perk_points() > 0 {
+                    ui.label(RichText::new("- perk available!").strong());
+                }
                 if pts > 0 {
                     // ui.label(format!("- {} points available", pts));
                     ui.label(
@@ -402,41 +509,99 @@ impl NetrunnerGame {
                 }
             })
             .body(|ui| {
-                let enabled: bool = match self.activity {
-                    Activity::FreeRoam => true,
-                    _ => false,
+                let enabled: bool = matches!(self.game.activity, Activity::FreeRoam);
+                let player = &self.game.player;
+                let skills = &player.skills;
+                let pending = &mut self.pending_skills;
+                let can_add: bool = pending.total_points() < pts;
+                let below_cap = |skill: &SkillType, committed: i32, pending: i32| {
+                    committed + pending < player.skill_cap(skill)
                 };
-                let can_add: bool = self.player.available_skill_points() > 0;
                 ui.add_enabled_ui(enabled, |ui| {
                     egui::Grid::new("some_unique_id").show(ui, |ui| {
                         // row: atk/def stats
                         ui.horizontal(|ui| {
-                            ui.label("Hacking: ")
-                                .on_hover_text("Increases attack damage");
-                            ui_counter(ui, &mut self.player.skills.hacking, can_add);
+                            ui.label("Hacking: ").on_hover_text(format!(
+                                "Increases attack damage (cap {})",
+                                player.skill_cap(&SkillType::Hacking)
+                            ));
+                            let can_add = can_add
+                                && below_cap(&SkillType::Hacking, skills.hacking, pending.hacking);
+                            ui_counter(ui, skills.hacking, &mut pending.hacking, can_add);
                         });
                         ui.horizontal(|ui| {
                             ui.separator();
-                            ui.label("Security: ")
-                                .on_hover_text("Mitigates enemy hacks");
-                            ui_counter(ui, &mut self.player.skills.security, can_add);
+                            ui.label("Security: ").on_hover_text(format!(
+                                "Mitigates enemy hacks (cap {})",
+                                player.skill_cap(&SkillType::Security)
+                            ));
+                            let can_add = can_add
+                                && below_cap(
+                                    &SkillType::Security,
+                                    skills.security,
+                                    pending.security,
+                                );
+                            ui_counter(ui, skills.security, &mut pending.security, can_add);
                         });
                         ui.end_row();
                     });
                 });
+                if self.pending_skills.total_points() > 0 {
+                    ui.horizontal(|ui| {
+                        if ui.button("Confirm").clicked() {
+                            let points =
+                                std::mem::replace(&mut self.pending_skills, Skills::empty());
+                            self.act(Action::AllocateSkills(points));
+                        }
+                        if ui.button("Cancel").clicked() {
+                            self.pending_skills = Skills::empty();
+                        }
+                    });
+                }
+                self.perk_list(ui, enabled);
+                for blueprint in self.game.player.blueprints.iter() {
+                    ui.label(format!("Blueprint: {}", blueprint.name()))
+                        .on_hover_text(blueprint.description());
+                }
             });
 
         // egui::CollapsingHeader::new(label.as_str()).show(ui, |ui| {
         // });
     }
 
+    fn perk_list(&mut self, ui: &mut egui::Ui, enabled: bool) {
+        let player = &self.game.player;
+        for perk in player.perks.iter() {
+            ui.label(format!("{}: {}", perk.name(), perk.description()));
+        }
+        if player.available_perk_points() <= 0 {
+            return;
+        }
+        ui.label(RichText::new("Pick a perk:").strong());
+        let mut chosen = None;
+        ui.add_enabled_ui(enabled, |ui| {
+            for perk in Perk::ALL.iter().filter(|p| !player.has_perk(**p)) {
+                if ui
+                    .button(perk.name())
+                    .on_hover_text(perk.description())
+                    .clicked()
+                {
+                    chosen = Some(*perk);
+                }
+            }
+        });
+        if let Some(perk) = chosen {
+            self.act(Action::ChoosePerk(perk));
+        }
+    }
+
     fn quest_panel(&mut self, ui: &mut egui::Ui) {
         if self
+            .game
             .player
             .quests
             .iter()
             .filter(|&(_, q)| q.trackable())
-            .into_iter()
             .count()
             == 0
         {
@@ -445,17 +610,14 @@ impl NetrunnerGame {
 
         ui.horizontal(|ui| {
             // show edit button in free roam
-            match self.activity {
-                Activity::FreeRoam => {
-                    self.edit_tracked_quests_button(ui);
-                }
-                _ => {}
+            if let Activity::FreeRoam = self.game.activity {
+                self.edit_tracked_quests_button(ui);
             }
             ui.label("Tracked Quests:");
         });
         let mut tracked_quests_counter: i32 = 0;
-        let is_editing: bool = self.player.has_flag(&PlayerFlag::EditingTrackedQuests);
-        for (_, quest) in self.player.quests.iter_mut() {
+        let is_editing: bool = self.game.player.has_flag(&PlayerFlag::EditingTrackedQuests);
+        for (_, quest) in self.game.player.quests.iter_mut() {
             if !is_editing && quest.tracked && quest.trackable() {
                 // show just quest name
                 ui.label(format!("        {}", quest.name()));
@@ -464,7 +626,7 @@ impl NetrunnerGame {
                 // show editing buttons
                 ui.horizontal(|ui| {
                     ui.checkbox(&mut quest.tracked, "");
-                    ui.label(format!("{}", quest.name()));
+                    ui.label(quest.name());
                 });
                 tracked_quests_counter += 1;
             };
@@ -475,10 +637,64 @@ impl NetrunnerGame {
         ui.separator();
     }
 
+    /// every quest the player knows about, grouped by where it stands
+    fn quest_log(&mut self, ui: &mut egui::Ui) {
+        let quests = &self.game.player.quests;
+        let known: Vec<_> = quests::quest_defs()
+            .iter()
+            .filter_map(|def| quests.get(&def.id).map(|quest| (def, quest)))
+            .filter(|(_, quest)| quest.is_started())
+            .collect();
+        let can_claim = matches!(self.game.activity, Activity::FreeRoam);
+        let mut claim = None;
+
+        ui.heading("Active");
+        for (def, quest) in known
+            .iter()
+            .filter(|(_, quest)| quest.is_active() && !quest.is_finished())
+        {
+            quest_log_entry(ui, def, quest);
+        }
+        ui.separator();
+        ui.heading("Available");
+        for (def, quest) in known
+            .iter()
+            .filter(|(_, quest)| !quest.is_active() && !quest.is_finished())
+        {
+            quest_log_entry(ui, def, quest);
+            let needs: Vec<String> = def
+                .prerequisites
+                .iter()
+                .map(|condition| condition.to_string())
+                .collect();
+            ui.label(RichText::new(format!("Needs: {}", needs.join(", "))).weak());
+        }
+        ui.separator();
+        ui.heading("Completed");
+        for (def, quest) in known.iter().filter(|(_, quest)| quest.is_finished()) {
+            quest_log_entry(ui, def, quest);
+            ui.horizontal(|ui| {
+                if let Some(turn) = quest.completed_turn() {
+                    ui.label(RichText::new(format!("Completed on turn {}", turn)).weak());
+                }
+                if quest.reward_waiting()
+                    && ui
+                        .add_enabled(can_claim, egui::Button::new("Claim rewards"))
+                        .clicked()
+                {
+                    claim = Some(def.id.clone());
+                }
+            });
+        }
+        if let Some(quest_id) = claim {
+            self.act(Action::ClaimQuestReward(quest_id));
+        }
+    }
+
     fn edit_tracked_quests_button(&mut self, ui: &mut egui::Ui) {
         let text_color: Color32;
         let bg_color: Color32;
-        if self.player.has_flag(&PlayerFlag::EditingTrackedQuests) {
+        if self.game.player.has_flag(&PlayerFlag::EditingTrackedQuests) {
             text_color = Color32::LIGHT_RED;
             bg_color = Color32::DARK_GRAY;
         } else {
@@ -493,154 +709,255 @@ impl NetrunnerGame {
             )
             .clicked()
         {
-            self.player.toggle_flag(PlayerFlag::EditingTrackedQuests)
+            self.game
+                .player
+                .toggle_flag(PlayerFlag::EditingTrackedQuests)
         };
     }
 
     fn list_available_networks(&mut self, ui: &mut egui::Ui) {
         // you can only change networks in free roam
-        let enabled = match self.activity {
-            Activity::FreeRoam => true,
-            _ => false,
-        };
+        let enabled = matches!(self.game.activity, Activity::FreeRoam);
+        let mut selected_net = self.game.current_net.clone();
         ui.add_enabled_ui(enabled, |ui| {
             ui.horizontal(|ui| {
                 ui.label("Network: ");
                 // ComboBox to select networks
                 egui::ComboBox::from_label("")
-                    .selected_text(format!("{:?}", self.current_net))
+                    .selected_text(format!("{:?}", selected_net))
                     .show_ui(ui, |ui| {
-                        // internet always available
-                        ui.selectable_value(&mut self.current_net, Networks::Internet, "Internet");
-                        // SIPRnet only after you unlock it
-                        if self.player.has_flag(&PlayerFlag::UnlockedNetworkSIPR) {
-                            ui.selectable_value(
-                                &mut self.current_net,
-                                Networks::SIPRnet,
-                                "SIPRNet",
-                            );
+                        for (net, label) in [
+                            (Networks::Internet, "Internet"),
+                            (Networks::SIPRnet, "SIPRNet"),
+                        ] {
+                            if self.game.network_available(&net) {
+                                ui.selectable_value(&mut selected_net, net, label);
+                            }
                         }
                     });
-                ui.label(format!(
-                    "{:.0} net intel",
-                    self.player_current_net_stats().total_intel
-                ));
+                ui.label(format!("{:.0} net intel", self.game.current_intel()));
             });
         });
-        match self.current_net {
+        if selected_net != self.game.current_net {
+            self.act(Action::ChangeNetwork(selected_net));
+        }
+        match self.game.current_net {
             Networks::Internet => ui.label("You are browsing the public internet."),
             Networks::SIPRnet => ui.label("You are logged in to the US DoD's classified network."),
         };
+        if let Some(hint) = self.game.unlock_hint(&Networks::SIPRnet) {
+            ui.label(RichText::new(format!("SIPRNet is locked: {}", hint)).weak());
+        }
     }
 
     fn combat_window(&mut self, ui: &mut egui::Ui) {
-        if let Activity::Combat(contacts) = &self.activity {
-            for contact in contacts {
-                // ui.horizontal(|ui| {
-                ui.heading(RichText::new("Threat Detected").color(Color32::from_rgb(200, 100, 0)));
-                ui.label(format!("Contact: {}", contact.name));
-                ui.label(colored_label(
-                    "HP",
-                    contact.hp.value,
-                    contact.hp.upper_limit,
-                ));
-                ui.label(format!("Disposition: {}", contact.disposition));
-                // });
-            }
+        if let Activity::Combat(contacts) = &self.game.activity {
+            // contacts drop out of the fight, keep the selection on one that's left
+            self.selected_target = self.selected_target.min(contacts.len().saturating_sub(1));
+            ui.heading(RichText::new("Threat Detected").color(Color32::from_rgb(200, 100, 0)));
+            ui.horizontal(|ui| {
+                for (index, contact) in contacts.iter().enumerate() {
+                    let selected = index == self.selected_target;
+                    let stroke = if selected {
+                        egui::Stroke::new(2.0, Color32::from_rgb(200, 100, 0))
+                    } else {
+                        ui.visuals().widgets.noninteractive.bg_stroke
+                    };
+                    egui::Frame::group(ui.style())
+                        .stroke(stroke)
+                        .show(ui, |ui| {
+                            ui.vertical(|ui| {
+                                if ui
+                                    .selectable_label(
+                                        selected,
+                                        format!("Contact: {}", contact.name),
+                                    )
+                                    .clicked()
+                                {
+                                    self.selected_target = index;
+                                }
+                                ui.label(colored_label(
+                                    "HP",
+                                    contact.hp.value,
+                                    contact.hp.upper_limit,
+                                ));
+                                ui.label(format!("Disposition: {}", contact.disposition));
+                                ui.label(format!("Type: {}", contact.archetype));
+                                if let Some(turns) =
+                                    contact.buffs.has_buff(BuffType::FirewallFortified)
+                                {
+                                    ui.label(
+                                        RichText::new(format!("Fortified ({} turns)", turns))
+                                            .color(Color32::LIGHT_BLUE),
+                                    );
+                                }
+                                if let Some(intent) = contact.intent {
+                                    let color = match intent.ability {
+                                        ContactAbility::Strike
+                                        | ContactAbility::InjectMalware
+                                        | ContactAbility::DrainRam => Color32::LIGHT_RED,
+                                        _ => Color32::YELLOW,
+                                    };
+                                    ui.label(
+                                        RichText::new(format!("Intent: {}", intent)).color(color),
+                                    );
+                                }
+                            });
+                        });
+                }
+            });
         }
         ui.horizontal(|ui| {
-            let attack_cost = 4;
-            if ui.button("Launch Hack").clicked() {
-                if self.player.ram.value >= attack_cost {
-                    self.player.ram.change_by(-attack_cost);
-                    self.combat_attack();
-                    self.do_turn();
-                } else {
-                    self.terminal_print(
-                        format!("You need {} RAM to use that ability.", attack_cost).as_str(),
-                    )
+            let statuses = [
+                (BuffType::MalwareInjected, Color32::LIGHT_RED),
+                (BuffType::FirewallFortified, Color32::LIGHT_BLUE),
+                (BuffType::Overclock, Color32::YELLOW),
+            ];
+            for (buff, color) in statuses {
+                if let Some(turns) = self.game.player.buffs.has_buff(buff) {
+                    ui.label(
+                        RichText::new(format!("{} ({} turns)", buff.name(), turns)).color(color),
+                    );
                 }
             }
-            let overclock_cost = 10;
-            if ui.button("Overclock Systems").clicked() {
-                if self.player.ram.value >= overclock_cost {
-                    self.player.ram.change_by(-overclock_cost);
-                    self.ability_overclock();
+        });
+        ui.horizontal(|ui| {
+            let mut used = None;
+            for ability in self.game.player.abilities.iter() {
+                let cost = self.game.ability_cost(*ability);
+                let cooldown = self.game.player.ability_cooldown(*ability);
+                let label = if cooldown > 0 {
+                    format!("{} ({} turns)", ability.name(), cooldown)
                 } else {
-                    self.terminal_print(
-                        format!("You need {} RAM to use that ability.", overclock_cost).as_str(),
-                    )
+                    format!("{} ({} RAM)", ability.name(), cost)
+                };
+                let purge = self.game.require_something_to_purge(*ability);
+                let ready = cooldown == 0 && self.game.player.ram.value >= cost && purge.is_ok();
+                let description = ability_def(ability).description();
+                if ui
+                    .add_enabled(ready, egui::Button::new(label))
+                    .on_hover_text(description.as_str())
+                    .on_disabled_hover_text(match purge {
+                        Err(err) => err.to_string(),
+                        Ok(()) => description,
+                    })
+                    .clicked()
+                {
+                    used = Some(*ability);
                 }
             }
+            if let Some(ability) = used {
+                self.act(Action::UseAbility {
+                    ability,
+                    target: self.selected_target,
+                });
+            }
             if ui
                 .button(RichText::new("Escape Combat").color(Color32::GRAY))
                 .clicked()
             {
-                self.activity = Activity::FreeRoam;
-                self.terminal_print("You escape from combat.");
-                self.player
-                    .net_stats
-                    .get_mut(&self.current_net)
-                    .unwrap()
-                    .total_intel -= 7.0;
+                self.act(Action::Escape);
             }
         });
+        self.inventory_list(ui, true);
+    }
+
+    fn inventory_list(&mut self, ui: &mut egui::Ui, in_combat: bool) {
+        let inventory = &self.game.player.inventory;
+        if inventory.is_empty() {
+            return;
+        }
+        let mut used = None;
+        ui.label("Inventory");
+        for (item, count) in inventory.iter() {
+            ui.horizontal(|ui| {
+                ui.label(format!("{} x{}", item.name(), count))
+                    .on_hover_text(item.description());
+                if (in_combat || !item.combat_only()) && ui.small_button("Use").clicked() {
+                    used = Some(item);
+                }
+            });
+        }
+        if let Some(item) = used {
+            self.act(Action::UseItem(item));
+        }
     }
 
     fn shop_for_upgrades(&mut self, ui: &mut egui::Ui) {
-        if self.player.has_flag(&PlayerFlag::DiscoveredShopBasic) {
+        if self.game.player.has_flag(&PlayerFlag::DiscoveredShopBasic) {
             ui.label("\"Welcome back. Here for an upgrade?\"");
         } else {
             ui.label("\"I don't recognize you, must be new here. Let's do biz.\"");
         };
         let mut available_upgrades = vec![];
-        for (_, upgrade) in self.player.upgrades.iter() {
-            if upgrade.available {
+        for def in CATALOG.iter() {
+            let upgrade = &self.game.player.upgrades[&def.upgrade_type];
+            if upgrade.available
+                && !upgrade.is_maxed()
+                && self.game.current_intel_level().0 >= def.min_intel_level
+            {
                 available_upgrades.push((
                     upgrade.upgrade_type.clone(),
                     upgrade.level,
                     upgrade.cost(),
+                    def.description(),
                 ))
             }
         }
-        for (up_type, up_lvl, up_cost) in available_upgrades.iter() {
+        let refund = self.game.respec_refund();
+        if refund > 0 {
+            ui.horizontal(|ui| {
+                ui.label(format!(
+                    "Respec: refund {} skill points for {}c",
+                    refund,
+                    refund * RESPEC_COST_PER_POINT
+                ));
+                if ui.button("Wipe it").clicked() {
+                    self.pending_skills = Skills::empty();
+                    self.act(Action::Respec);
+                };
+            });
+        }
+        let intel_level = self.game.current_intel_level().0;
+        for item in ItemType::ALL
+            .into_iter()
+            .filter(|item| intel_level >= item.min_intel_level())
+        {
+            ui.horizontal(|ui| {
+                ui.label(format!("'{}' for {}c", item.name(), item.price()))
+                    .on_hover_text(item.description());
+                if ui.button("Buy it").clicked() {
+                    self.act(Action::BuyItem(item));
+                };
+            });
+        }
+        if let Some(cost) = self.game.network_access_price(&Networks::SIPRnet) {
+            ui.horizontal(|ui| {
+                ui.label(format!("'SIPRNet credentials' for {}c", cost))
+                    .on_hover_text("Unlocks SIPRNet");
+                if ui.button("Buy it").clicked() {
+                    self.act(Action::BuyNetworkAccess(Networks::SIPRnet));
+                };
+            });
+        }
+        for (up_type, up_lvl, up_cost, up_desc) in available_upgrades {
             ui.horizontal(|ui| {
                 ui.label(format!(
                     "'{}' level {} for {}c",
                     up_type.name(),
                     up_lvl + 1,
                     up_cost
-                ));
-                if ui.button("Buy it").clicked() && self.player.credits >= *up_cost as i32 {
-                    self.player.credits -= *up_cost as i32;
-                    self.do_upgrade_effect(up_type);
-                    self.terminal_print(format!("You bought {}!", up_type.name()).as_str());
+                ))
+                .on_hover_text(up_desc);
+                if ui.button("Buy it").clicked() {
+                    self.act(Action::BuyUpgrade(up_type));
                 };
             });
         }
     }
 
-    fn do_upgrade_effect(&mut self, upgrade: &PlayerUpgradeType) {
-        // increase upgrade level by 1 and apply the upgrade's effects
-        self.player.upgrades.get_mut(upgrade).unwrap().level += 1;
-        match upgrade {
-            PlayerUpgradeType::HPMaxUp => {
-                self.player.hp.upper_limit += 50;
-                self.player.hp.value += 50
-            }
-            PlayerUpgradeType::SecurityUp => todo!(),
-        }
-    }
-
-    fn player_current_net_stats(&self) -> &NetStats {
-        self.player.net_stats.get(&self.current_net).unwrap()
-    }
-
     fn net_intel_bar(&mut self, ui: &mut egui::Ui) {
-        let total_intel = self.player_current_net_stats().total_intel;
-        let per_level_cost = 200.0 * self.current_net.difficulty();
-        let intel_level = (total_intel / per_level_cost).floor();
-        let progress = (total_intel % per_level_cost) / per_level_cost;
+        let (intel_level, progress) = self.game.current_intel_level();
         ui.horizontal(|ui| {
             ui.label(format!(
                 "Intel level: {} ({:.1}%)",
@@ -658,22 +975,28 @@ impl NetrunnerGame {
             ui.selectable_value(&mut self.current_task, Tasks::Recover, "Recover");
             ui.selectable_value(&mut self.current_task, Tasks::Search, "Search around");
             ui.selectable_value(&mut self.current_task, Tasks::Datamine, "Datamine");
+            let needed = Tasks::Exfiltrate.min_intel_level();
+            if self.game.current_intel_level().0 >= needed {
+                ui.selectable_value(&mut self.current_task, Tasks::Exfiltrate, "Exfiltrate");
+            } else {
+                ui.add_enabled(false, egui::SelectableLabel::new(false, "Exfiltrate"))
+                    .on_disabled_hover_text(format!("Needs intel level {}", needed));
+            }
         });
-        ui.label(self.current_task.description());
+        ui.label(task_description(&self.current_task));
         ui.horizontal(|ui| {
             if ui.button("Do Task").clicked() {
-                self.do_task()
+                self.act(Action::DoTask(self.current_task))
             }
-            if self.player.has_flag(&PlayerFlag::DiscoveredShopBasic) {
-                if ui
+            if self.game.player.has_flag(&PlayerFlag::DiscoveredShopBasic)
+                && ui
                     .button(RichText::new("Enter Shop").color(Color32::GRAY))
                     .clicked()
-                {
-                    // self.state = ActivityInteracting(InteractionType::BasicShop);
-                    self.go_shopping();
-                }
+            {
+                self.act(Action::EnterShop);
             };
         });
+        self.inventory_list(ui, false);
     }
 
     fn interaction_window(&mut self, int_type: InteractionType, ui: &mut egui::Ui) {
@@ -682,16 +1005,58 @@ impl NetrunnerGame {
                 ui.heading("Shopping");
                 self.shop_for_upgrades(ui);
                 if ui.button("Exit Shop").clicked() {
-                    self.activity = Activity::FreeRoam;
-                    self.player.enable_flag(PlayerFlag::DiscoveredShopBasic)
+                    self.act(Action::LeaveShop);
                 };
             }
         }
     }
 
+    fn flatline_window(&mut self, ui: &mut egui::Ui) {
+        ui.heading(RichText::new("FLATLINED").color(Color32::from_rgb(200, 40, 40)));
+        if let Activity::Flatlined {
+            killed_by: Some(name),
+        } = &self.game.activity
+        {
+            ui.label(format!("Killed by {}", name));
+        }
+        if !self.game.settings.ironman {
+            if ui.button("Reboot from backup").clicked() {
+                self.act(Action::Respawn);
+            }
+            return;
+        }
+        ui.label("Ironman: this run is over.");
+        let past_runs = self.past_runs.get_or_insert_with(|| {
+            records::load_records(Path::new(records::RECORDS_PATH))
+                .map(|mut runs| {
+                    runs.sort_by_key(|run| std::cmp::Reverse((run.level, run.turns)));
+                    runs
+                })
+                .map_err(|err| err.to_string())
+        });
+        match past_runs {
+            Ok(runs) => {
+                ui.label("Best runs:");
+                for run in runs.iter().take(5) {
+                    ui.label(format!(
+                        "{} - level {}, {} turns, {} kills (seed {})",
+                        run.player_name, run.level, run.turns, run.kills, run.seed
+                    ));
+                }
+            }
+            Err(err) => {
+                ui.label(format!("Could not load past runs: {}", err));
+            }
+        }
+        if ui.button("Start a new run").clicked() {
+            self.new_run();
+        }
+    }
+
     fn convo_window(&mut self, ui: &mut egui::Ui) {
         // TODO - its own scrolling terminal maybe?
-        if let Activity::Conversing(ref mut convo) = self.activity {
+        let mut action = None;
+        if let Activity::Conversing(ref convo) = self.game.activity {
             ui.heading("Conversation");
             // conversation history
             let id = ui.make_persistent_id("convo_history");
@@ -702,35 +1067,92 @@ impl NetrunnerGame {
                 .body(|ui| ui.label(convo.show_lines_before()));
             // current line of the conversation
             ui.label(convo.show_line());
-            // continue/leave button
+            // responses, continue or leave
+            let responses = convo.responses();
             if convo.done() {
                 if ui.button("end convo").clicked() {
-                    self.activity = Activity::FreeRoam
+                    action = Some(Action::EndConversation);
                 }
-            } else {
+            } else if responses.is_empty() {
                 if ui.button("continue convo").clicked() {
-                    convo.next_line();
+                    action = Some(Action::ContinueConversation);
+                }
+            } else {
+                for (index, response) in responses.iter().enumerate() {
+                    let available = response.available(&self.game.player);
+                    let button = ui
+                        .add_enabled(available, egui::Button::new(format!("> {}", response.text)));
+                    if button.clicked() {
+                        action = Some(Action::ChooseResponse(index));
+                    }
+                    if !response.conditions.is_empty() {
+                        let requirements: Vec<String> = response
+                            .conditions
+                            .iter()
+                            .map(|condition| condition.to_string())
+                            .collect();
+                        button.on_disabled_hover_text(requirements.join(", "));
+                    }
                 }
             }
         }
+        if let Some(action) = action {
+            self.act(action);
+        }
     }
 }
 
-fn ui_counter(ui: &mut egui::Ui, counter: &mut i32, can_add: bool) {
+/// shows a committed value plus the points pending on top of it,
+/// pending points can be taken back but committed ones can't
+fn ui_counter(ui: &mut egui::Ui, committed: i32, pending: &mut i32, can_add: bool) {
     // Put the buttons and label on the same row:
     ui.horizontal(|ui| {
-        if ui.button("-").clicked() {
-            *counter -= 1;
+        if ui
+            .add_enabled(*pending > 0, egui::Button::new("-"))
+            .clicked()
+        {
+            *pending -= 1;
         }
-        ui.label(counter.to_string());
-        if can_add {
-            if ui.button("+").clicked() {
-                *counter += 1;
-            }
+        if *pending > 0 {
+            ui.label(RichText::new(format!("{} (+{})", committed, pending)).strong());
+        } else {
+            ui.label(committed.to_string());
+        }
+        if can_add && ui.button("+").clicked() {
+            *pending += 1;
         }
     });
 }
 
+/// name, description, objectives and rewards of one quest
+fn quest_log_entry(ui: &mut egui::Ui, def: &quests::QuestDef, quest: &quests::Quest) {
+    ui.label(RichText::new(&def.name).strong());
+    if !def.description.is_empty() {
+        ui.label(RichText::new(&def.description).weak());
+    }
+    for (index, objective) in def.objectives.iter().enumerate() {
+        if index < quest.step() {
+            ui.label(format!("  ✔ {}", objective));
+        } else if index > quest.step() {
+            ui.label(RichText::new(format!("  · {}", objective)).weak());
+        } else {
+            ui.horizontal(|ui| {
+                ui.label(format!("  ▶ {}", objective));
+                if let Some((progress, target)) = quest.objective_progress() {
+                    ui.add(
+                        egui::ProgressBar::new(progress as f32 / target as f32)
+                            .text(format!("{}/{}", progress, target)),
+                    );
+                }
+            });
+        }
+    }
+    if !def.rewards.is_empty() {
+        ui.label(format!("Rewards: {}", quests::reward_summary(&def.rewards)));
+    }
+    ui.add_space(4.0);
+}
+
 fn display_terminal(ui: &mut egui::Ui, terminal_lines: &Vec<String>) {
     egui::ScrollArea::vertical()
         .stick_to_bottom(true)
@@ -764,30 +1186,56 @@ impl eframe::App for NetrunnerGame {
         // 1 fps minimum even if unfocused
         ctx.request_repaint_after(MAX_WAIT_BETWEEN_FRAMES);
 
-        // adjust intel level over time
-        self.player
-            .net_stats
-            .get_mut(&self.current_net)
-            .unwrap()
-            .total_intel += delta_time.as_secs_f32() * 1.0;
+        // adjust intel level over time, replays bring their own idle time
+        if self.replayer.is_none() {
+            self.game.accrue_intel(delta_time.as_secs_f32() * 1.0);
+        }
 
         // render GUI
-        let browse_flavor_txt = match self.activity {
+        let browse_flavor_txt = match self.game.activity {
             Activity::Combat(_) => "Engaged in combat",
             _ => "Cruising the net",
         };
         egui::TopBottomPanel::top("my_panel").show(ctx, |ui| {
             ui.horizontal(|ui| {
-                ui.heading(format!("{}", browse_flavor_txt,));
+                ui.heading(browse_flavor_txt);
                 ui.add_space(11.0);
-                ui.label(RichText::new(format!("latency: {} ms", delta_time.as_millis())).weak())
-            })
+                ui.label(RichText::new(format!("latency: {} ms", delta_time.as_millis())).weak());
+                ui.label(RichText::new(format!("seed: {}", self.game.seed)).weak());
+                if self.game.settings.ironman {
+                    ui.label(RichText::new("ironman").weak());
+                }
+                if self.replayer.is_none() {
+                    if ui.small_button("Save").clicked() {
+                        self.save();
+                    }
+                    if ui.small_button("Export replay").clicked() {
+                        self.export_replay();
+                    }
+                }
+                if ui.small_button("Quest log").clicked() {
+                    self.show_quest_log = !self.show_quest_log;
+                }
+            });
+            self.replay_controls(ui);
         });
 
+        let mut show_quest_log = self.show_quest_log;
+        egui::Window::new("Quest log")
+            .open(&mut show_quest_log)
+            .vscroll(true)
+            .show(ctx, |ui| {
+                ui.set_enabled(self.replayer.is_none());
+                self.quest_log(ui);
+            });
+        self.show_quest_log = show_quest_log;
+
         egui::CentralPanel::default().show(ctx, |ui| {
+            // the replay drives the game, not the player
+            ui.set_enabled(self.replayer.is_none());
             // ui.horizontal(|ui| {
             //     let name_label = ui.label("Your name: ");
-            //     ui.text_edit_singleline(&mut self.player.name)
+            //     ui.text_edit_singleline(&mut self.game.player.name)
             //         .labelled_by(name_label.id);
             // });
             self.player_stats_table(ui);
@@ -795,16 +1243,16 @@ impl eframe::App for NetrunnerGame {
             // ui.separator();
             // list available networks
             self.list_available_networks(ui);
-            // self.net_intel_bar(ui);
+            self.net_intel_bar(ui);
             ui.separator();
             // quests go here
 
             self.quest_panel(ui);
-            if self.player.has_flag(&PlayerFlag::EditingTrackedQuests) {
+            if self.game.player.has_flag(&PlayerFlag::EditingTrackedQuests) {
                 ui.label("Click checkboxes next to quests to track them");
                 ui.label("Click the '✏' to finish editing tracked quests");
             } else {
-                match self.activity {
+                match self.game.activity {
                     Activity::FreeRoam => self.list_available_tasks(ui),
                     Activity::Combat(..) => self.combat_window(ui),
                     Activity::Interacting(int_type) => {
@@ -813,13 +1261,24 @@ impl eframe::App for NetrunnerGame {
                     Activity::Conversing(_) => {
                         self.convo_window(ui);
                     }
+                    Activity::Flatlined { .. } => self.flatline_window(ui),
                 }
             }
-            // if ui.button("DEBUG: convo").clicked() {
-            //     self.activity = Activity::Conversing(Conversation::test())
-            // }
             ui.separator();
             display_terminal(ui, &self.terminal_lines);
         });
     }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        if self.replayer.is_some() || !self.autosave {
+            // don't clobber the real save with a replay, or one that failed to load
+            return;
+        }
+        // autosave so a run can span multiple sessions
+        if let Err(err) =
+            save::save_game(&self.game, &self.terminal_lines, Path::new(save::SAVE_PATH))
+        {
+            eprintln!("Could not save on exit: {}", err);
+        }
+    }
 }
@@ -1,34 +1,13 @@
-use rand::{random, seq::SliceRandom, thread_rng};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
+use crate::behaviour::{random_hostile, Archetype, Behaviour, ContactAbility, Intent};
+use crate::buffs::BuffContainer;
+
 pub const BASE_SKILL_POINTS: i32 = 10;
 
-fn random_hostile_name() -> String {
-    let vs: Vec<&str> = vec![
-        "adware-imp",
-        "maniabot",
-        "SpamSpyder",
-        "darknet-dragon",
-        "silent-strike",
-        "phantom_protocol",
-    ];
-    return vs.choose(&mut thread_rng()).unwrap().to_string();
-}
-
-fn random_gov_hostile_name() -> String {
-    let vs: Vec<&str> = vec![
-        "VigilanceCore",
-        "PulseDefender",
-        "AegisProtocol",
-        "CyberSam",
-        "ApexSentinel",
-        "EtherealVigil",
-        "QuantumSafeguard",
-    ];
-    return vs.choose(&mut thread_rng()).unwrap().to_string();
-}
-
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub enum Disposition {
     Neutral,
     Hostile,
@@ -40,57 +19,99 @@ impl fmt::Display for Disposition {
     }
 }
 
+#[derive(Serialize, Deserialize)]
 pub struct Contact {
     pub name: String,
+    pub level: i32,
     pub hp: CappedValue,
     pub skills: Skills,
     pub disposition: Disposition,
+    pub archetype: Archetype,
+    pub abilities: Vec<ContactAbility>,
+    pub behaviour: Behaviour,
+    pub buffs: BuffContainer,
+    /// what it does on its next turn, picked at the start of each round
+    pub intent: Option<Intent>,
 }
 
 impl Contact {
-    pub fn new(level: i32, net: &Networks) -> Self {
+    pub fn new(level: i32, net: &Networks, rng: &mut impl Rng) -> Self {
         let total_skill_points = BASE_SKILL_POINTS * level;
         let range: f32 = total_skill_points as f32 / 4.0;
         let r_skill: i32 = ((total_skill_points as f32 / 2.0)
-            + ((random::<f32>() - 0.5) * 2.0 * range))
+            + ((rng.gen::<f32>() - 0.5) * 2.0 * range))
             .round() as i32;
         let health = 25 + (level * 5);
-        let name = match net {
-            Networks::Internet => random_hostile_name(),
-            Networks::SIPRnet => random_gov_hostile_name(),
-        };
-        Self {
-            name: name,
-            hp: CappedValue::new_health(health),
-            skills: Skills {
+        let (name, archetype) = random_hostile(net, rng);
+        Self::with_archetype(
+            name,
+            archetype,
+            level,
+            health,
+            Skills {
                 hacking: r_skill,
                 security: total_skill_points - r_skill,
             },
+        )
+    }
+
+    fn with_archetype(
+        name: String,
+        archetype: Archetype,
+        level: i32,
+        health: i32,
+        skills: Skills,
+    ) -> Self {
+        Self {
+            name,
+            level,
+            hp: CappedValue::new_health(health),
+            skills,
             disposition: Disposition::Hostile,
+            archetype,
+            abilities: archetype.abilities().to_vec(),
+            behaviour: archetype.behaviour(),
+            buffs: BuffContainer::new(),
+            intent: None,
         }
     }
 
+    /// a weaker copy a swarm brings into the fight
+    pub fn subprocess(&self) -> Self {
+        let level = (self.level / 2).max(1);
+        Self::with_archetype(
+            format!("{}.sub", self.name),
+            Archetype::Subprocess,
+            level,
+            10 + (level * 3),
+            Skills {
+                hacking: self.skills.hacking / 2,
+                security: self.skills.security / 2,
+            },
+        )
+    }
+
+    /// XP for taking it down; a swarm's copies are worth nothing, or stalling a swarm would farm them
     pub fn reward(&self) -> i32 {
+        if self.is_subprocess() {
+            return 0;
+        }
         self.skills.total_points() + (self.hp.upper_limit / 10)
     }
-}
 
-impl Default for Contact {
-    fn default() -> Self {
-        Self {
-            name: random_hostile_name(),
-            hp: CappedValue::new_health(30),
-            skills: Skills::default(),
-            disposition: Disposition::Hostile,
-        }
+    /// spawned mid-fight, so taking it down earns no loot, intel or quest progress
+    pub fn is_subprocess(&self) -> bool {
+        self.archetype == Archetype::Subprocess
     }
 }
 
+#[derive(Serialize, Deserialize)]
 pub enum CappedValueType {
     Health,
     Ram,
 }
 
+#[derive(Serialize, Deserialize)]
 pub struct CappedValue {
     pub value: i32,
     pub upper_limit: i32,
@@ -100,7 +121,7 @@ pub struct CappedValue {
 impl CappedValue {
     pub fn new_health(value: i32) -> Self {
         Self {
-            value: value,
+            value,
             upper_limit: value,
             value_type: CappedValueType::Health,
         }
@@ -108,37 +129,72 @@ impl CappedValue {
 
     pub fn new_ram(value: i32, upper_limit: i32) -> Self {
         Self {
-            value: value,
-            upper_limit: upper_limit,
+            value,
+            upper_limit,
             value_type: CappedValueType::Ram,
         }
     }
 
-    fn hit_zero(&self) {
-        // match self.value_type {
-        //     CappedValueType::Health => {
-        //         println!("oh no you're dead")
-        //     }
-        //     CappedValueType::Ram => println!("oh no you're out of RAM"),
-        // }
-    }
-
     pub fn change_by(&mut self, amount: i32) {
         self.value = self.upper_limit.min((self.value + amount).max(0));
-        if self.value == 0 {
-            self.hit_zero();
-        }
     }
 }
 
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Skills {
     pub hacking: i32,
     pub security: i32,
 }
 
 impl Skills {
+    /// no points in anything, e.g. an allocation nothing has been put into yet
+    pub fn empty() -> Self {
+        Self {
+            hacking: 0,
+            security: 0,
+        }
+    }
+
     pub fn total_points(&self) -> i32 {
-        return self.hacking + self.security;
+        self.hacking + self.security
+    }
+
+    pub fn get(&self, skill: &SkillType) -> i32 {
+        match skill {
+            SkillType::Hacking => self.hacking,
+            SkillType::Security => self.security,
+        }
+    }
+
+    pub fn get_mut(&mut self, skill: &SkillType) -> &mut i32 {
+        match skill {
+            SkillType::Hacking => &mut self.hacking,
+            SkillType::Security => &mut self.security,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SkillType {
+    Hacking,
+    Security,
+}
+
+impl SkillType {
+    pub const ALL: [SkillType; 2] = [SkillType::Hacking, SkillType::Security];
+
+    /// committed points can't go below this, not even with a respec
+    pub fn floor(&self) -> i32 {
+        match self {
+            SkillType::Hacking => 2,
+            SkillType::Security => 1,
+        }
+    }
+}
+
+impl fmt::Display for SkillType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
     }
 }
 
@@ -151,7 +207,7 @@ impl Default for Skills {
     }
 }
 
-#[derive(Eq, PartialEq, Hash, Debug)]
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize, Deserialize)]
 pub enum Networks {
     Internet,
     SIPRnet,
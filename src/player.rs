@@ -1,26 +1,44 @@
-use rand::{seq::SliceRandom, thread_rng};
-use std::collections::HashMap;
+use rand::{seq::SliceRandom, Rng};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
+use crate::abilities::{AbilityType, STARTING_ABILITIES};
 use crate::buffs::BuffContainer;
-use crate::pieces::{CappedValue, Networks, Skills, BASE_SKILL_POINTS};
+use crate::engine::Tasks;
+use crate::events::{EventListener, GameEvent};
+use crate::items::Inventory;
+use crate::loot::Blueprint;
+use crate::pieces::{CappedValue, Networks, SkillType, Skills, BASE_SKILL_POINTS};
 use crate::quests::{default_quests, Quest, QuestID};
+use crate::upgrades::{upgrade_def, UpgradeDef, UpgradeEffect, CATALOG};
 
+#[derive(Default, Serialize, Deserialize)]
 pub struct PlayerStats {
     pub kills: u32,
     pub datamine_success: u32,
     pub search_success: u32,
+    pub flatlines: u32,
 }
 
-impl Default for PlayerStats {
-    fn default() -> Self {
-        Self {
-            kills: 0,
-            datamine_success: 0,
-            search_success: 0,
+impl EventListener for PlayerStats {
+    fn on_event(&mut self, event: &GameEvent) {
+        match event {
+            GameEvent::ContactDefeated { .. } => self.kills += 1,
+            GameEvent::TaskSucceeded {
+                task: Tasks::Datamine,
+                ..
+            } => self.datamine_success += 1,
+            GameEvent::TaskSucceeded {
+                task: Tasks::Search,
+                ..
+            } => self.search_success += 1,
+            GameEvent::Flatlined { .. } => self.flatlines += 1,
+            _ => {}
         }
     }
 }
 
+#[derive(Serialize, Deserialize)]
 pub struct NetStats {
     pub total_intel: f32,
 }
@@ -31,61 +49,295 @@ impl Default for NetStats {
     }
 }
 
-#[derive(PartialEq, Eq, Hash, Clone)]
+/// intel it takes to go up one level on a network of difficulty 1.0
+pub const INTEL_PER_LEVEL: f32 = 200.0;
+
+/// (intel level, progress towards the next one) for `total_intel` on `net`;
+/// levels start at 0 and harder networks take longer to learn
+pub fn intel_level(total_intel: f32, net: &Networks) -> (i32, f32) {
+    let per_level = INTEL_PER_LEVEL * net.difficulty();
+    let total_intel = total_intel.max(0.0);
+    (
+        (total_intel / per_level).floor() as i32,
+        (total_intel % per_level) / per_level,
+    )
+}
+
+/// only hears about events on its own network
+impl EventListener for NetStats {
+    fn on_event(&mut self, event: &GameEvent) {
+        let change = match event {
+            GameEvent::ContactDefeated { .. } => 12.0,
+            GameEvent::TaskSucceeded {
+                task: Tasks::Datamine,
+                ..
+            } => 3.0,
+            GameEvent::TaskSucceeded {
+                task: Tasks::Exfiltrate,
+                ..
+            } => 5.0,
+            // running leaves a trail the network learns from
+            GameEvent::Escaped(_) => -7.0,
+            GameEvent::Flatlined {
+                reset_intel: true, ..
+            } => -self.total_intel,
+            _ => 0.0,
+        };
+        self.total_intel = (self.total_intel + change).max(0.0);
+    }
+}
+
+impl NetStats {
+    pub fn intel_level(&self, net: &Networks) -> i32 {
+        intel_level(self.total_intel, net).0
+    }
+}
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Debug, Serialize, Deserialize)]
 pub enum PlayerUpgradeType {
     HPMaxUp,
+    RamMaxUp,
     SecurityUp,
+    HackingUp,
+    BackgroundDaemon,
 }
 
 impl PlayerUpgradeType {
     pub fn name(&self) -> String {
-        match *self {
-            PlayerUpgradeType::HPMaxUp => "HP Max +".to_string(),
-            PlayerUpgradeType::SecurityUp => "Sec Max +".to_string(),
-        }
+        upgrade_def(self).name.to_string()
     }
 }
 
+/// The player's progress on one upgrade from the catalog
+#[derive(Serialize, Deserialize)]
 pub struct PlayerUpgrade {
     pub upgrade_type: PlayerUpgradeType,
     pub level: u32,
-    base_cost: u32,
-    cost_per_level: u32,
+    /// stocked in the shop
     pub available: bool,
 }
 
 impl PlayerUpgrade {
+    pub fn def(&self) -> &'static UpgradeDef {
+        upgrade_def(&self.upgrade_type)
+    }
+
     pub fn cost(&self) -> u32 {
-        return self.base_cost + (self.level * self.cost_per_level);
+        self.def().cost(self.level)
     }
+
+    pub fn is_maxed(&self) -> bool {
+        self.level >= self.def().max_level
+    }
+}
+
+/// committed points in any one skill, before upgrades
+pub const BASE_SKILL_CAP: i32 = 10;
+
+/// XP needed to go from level 1 to level 2, each level after costs this much more
+pub const XP_PER_LEVEL: i32 = 50;
+pub const SKILL_POINTS_PER_LEVEL: i32 = 2;
+/// a perk can be picked every this many levels
+pub const LEVELS_PER_PERK: i32 = 3;
+
+/// total XP needed to reach `level`
+pub fn xp_for_level(level: i32) -> i32 {
+    XP_PER_LEVEL * (level - 1) * level / 2
 }
 
-#[derive(Debug, PartialEq, Eq)]
+/// Permanent bonuses picked as the player levels up
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Perk {
+    Hardened,
+    Overclocker,
+    DataHound,
+    QuickRecovery,
+}
+
+impl Perk {
+    pub const ALL: [Perk; 4] = [
+        Perk::Hardened,
+        Perk::Overclocker,
+        Perk::DataHound,
+        Perk::QuickRecovery,
+    ];
+
+    pub fn name(&self) -> String {
+        match *self {
+            Perk::Hardened => "Hardened".to_string(),
+            Perk::Overclocker => "Overclocker".to_string(),
+            Perk::DataHound => "Data Hound".to_string(),
+            Perk::QuickRecovery => "Quick Recovery".to_string(),
+        }
+    }
+
+    pub fn description(&self) -> String {
+        match *self {
+            Perk::Hardened => "+25 max HP".to_string(),
+            Perk::Overclocker => "Overclocking costs 4 less RAM".to_string(),
+            Perk::DataHound => "+25% credits from searching and datamining".to_string(),
+            Perk::QuickRecovery => "Recover 5 more HP and RAM when recovering".to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum PlayerFlag {
     DiscoveredShopBasic,
     EditingTrackedQuests,
+    /// the insider has offered a way into SIPRnet
+    MetSiprContact,
+    /// the fixer has handed over their contract
+    HiredByFixer,
     UnlockedNetworkSIPR,
 }
 
+#[derive(Serialize, Deserialize)]
 pub struct Player {
     pub name: String,
     pub stats: PlayerStats, // track for posterity
-    pub net_stats: HashMap<Networks, NetStats>,
+    pub net_stats: BTreeMap<Networks, NetStats>,
     pub skills: Skills, // skills for checks and such
     pub hp: CappedValue,
     pub ram: CappedValue,
     pub credits: i32,
+    pub inventory: Inventory,
+    pub data_fragments: i32,
+    pub blueprints: Vec<Blueprint>,
     pub xp: i32,
-    pub upgrades: HashMap<PlayerUpgradeType, PlayerUpgrade>,
+    pub upgrades: BTreeMap<PlayerUpgradeType, PlayerUpgrade>,
     pub flags: Vec<PlayerFlag>,
+    pub perks: Vec<Perk>,
+    /// one-off encounters that already happened, by conversation id
+    pub encounters_met: Vec<String>,
     pub buffs: BuffContainer,
-    pub quests: HashMap<QuestID, Quest>,
+    pub quests: BTreeMap<QuestID, Quest>,
+    /// abilities on the combat bar, in the order they're shown
+    pub abilities: Vec<AbilityType>,
+    /// turns left before an ability can be used again
+    pub ability_cooldowns: BTreeMap<AbilityType, u32>,
 }
 
 impl Player {
+    pub fn new(rng: &mut impl Rng) -> Self {
+        let mut upgrades = BTreeMap::new();
+        for def in CATALOG.iter() {
+            upgrades.insert(
+                def.upgrade_type.clone(),
+                PlayerUpgrade {
+                    upgrade_type: def.upgrade_type.clone(),
+                    level: 0,
+                    available: def.prerequisites.is_empty(),
+                },
+            );
+        }
+        let mut net_stats = BTreeMap::new();
+        net_stats.insert(Networks::Internet, NetStats::default());
+        net_stats.insert(Networks::SIPRnet, NetStats::default());
+        Self {
+            name: random_default_name(rng),
+            stats: PlayerStats::default(),
+            net_stats,
+            skills: Skills::default(),
+            hp: CappedValue::new_health(100),
+            ram: CappedValue::new_ram(50, 100),
+            credits: 0,
+            inventory: Inventory::default(),
+            data_fragments: 0,
+            blueprints: vec![],
+            xp: 0,
+            upgrades,
+            flags: vec![],
+            perks: vec![],
+            encounters_met: vec![],
+            buffs: BuffContainer::new(),
+            quests: default_quests(),
+            abilities: STARTING_ABILITIES.to_vec(),
+            ability_cooldowns: BTreeMap::new(),
+        }
+    }
+
+    pub fn level(&self) -> i32 {
+        let mut level = 1;
+        while self.xp >= xp_for_level(level + 1) {
+            level += 1;
+        }
+        level
+    }
+
+    /// (xp into the current level, xp the current level takes)
+    pub fn level_progress(&self) -> (i32, i32) {
+        let level = self.level();
+        let floor = xp_for_level(level);
+        (self.xp - floor, xp_for_level(level + 1) - floor)
+    }
+
+    /// every skill point the player has earned, spent or not
+    pub fn total_skill_points(&self) -> i32 {
+        BASE_SKILL_POINTS + SKILL_POINTS_PER_LEVEL * (self.level() - 1)
+    }
+
     pub fn available_skill_points(&self) -> i32 {
-        // debug - for now, 12 points is the max
-        return BASE_SKILL_POINTS - self.skills.total_points();
+        self.total_skill_points() - self.skills.total_points()
+    }
+
+    pub fn available_perk_points(&self) -> i32 {
+        self.level() / LEVELS_PER_PERK - self.perks.len() as i32
+    }
+
+    pub fn knows_ability(&self, ability: AbilityType) -> bool {
+        self.abilities.contains(&ability)
+    }
+
+    /// turns until `ability` is ready, 0 if it is
+    pub fn ability_cooldown(&self, ability: AbilityType) -> u32 {
+        self.ability_cooldowns.get(&ability).copied().unwrap_or(0)
+    }
+
+    pub fn has_perk(&self, perk: Perk) -> bool {
+        self.perks.contains(&perk)
+    }
+
+    pub fn upgrade_level(&self, upgrade_type: &PlayerUpgradeType) -> u32 {
+        self.upgrades
+            .get(upgrade_type)
+            .map(|upgrade| upgrade.level)
+            .unwrap_or(0)
+    }
+
+    /// every upgrade `def` depends on is at the required level
+    pub fn meets_prerequisites(&self, def: &UpgradeDef) -> bool {
+        def.prerequisites
+            .iter()
+            .all(|(upgrade_type, level)| self.upgrade_level(upgrade_type) >= *level)
+    }
+
+    /// sum of one kind of effect across every upgrade level bought
+    fn upgrade_bonus(&self, bonus: impl Fn(&UpgradeEffect) -> i32) -> i32 {
+        self.upgrades
+            .values()
+            .map(|upgrade| {
+                let per_level: i32 = upgrade.def().effects.iter().map(&bonus).sum();
+                per_level * upgrade.level as i32
+            })
+            .sum()
+    }
+
+    /// committed points in a skill can't go above this
+    pub fn skill_cap(&self, skill: &SkillType) -> i32 {
+        BASE_SKILL_CAP
+            + self.upgrade_bonus(|effect| match effect {
+                UpgradeEffect::SkillCap(capped, amount) if capped == skill => *amount,
+                _ => 0,
+            })
+    }
+
+    /// RAM regenerated every turn
+    pub fn ram_regen(&self) -> i32 {
+        self.upgrade_bonus(|effect| match effect {
+            UpgradeEffect::RamRegen(amount) => *amount,
+            _ => 0,
+        })
     }
 
     pub fn enable_flag(&mut self, flag: PlayerFlag) {
@@ -110,55 +362,15 @@ impl Player {
         }
     }
 
-    pub fn add_xp(&mut self, to_add: i32) {
+    /// returns how many levels the player gained
+    pub fn add_xp(&mut self, to_add: i32) -> i32 {
+        let level_before = self.level();
         self.xp += to_add;
+        self.level() - level_before
     }
 }
 
-impl Default for Player {
-    fn default() -> Self {
-        let mut upgrades = HashMap::new();
-        upgrades.insert(
-            PlayerUpgradeType::HPMaxUp,
-            PlayerUpgrade {
-                upgrade_type: PlayerUpgradeType::HPMaxUp,
-                level: 0,
-                cost_per_level: 50,
-                base_cost: 100,
-                available: true,
-            },
-        );
-        upgrades.insert(
-            PlayerUpgradeType::SecurityUp,
-            PlayerUpgrade {
-                upgrade_type: PlayerUpgradeType::SecurityUp,
-                level: 0,
-                cost_per_level: 300,
-                base_cost: 150,
-                available: false,
-            },
-        );
-        let mut net_stats = HashMap::new();
-        net_stats.insert(Networks::Internet, NetStats::default());
-        net_stats.insert(Networks::SIPRnet, NetStats::default());
-        Self {
-            name: random_default_name(),
-            stats: PlayerStats::default(),
-            net_stats: net_stats,
-            skills: Skills::default(),
-            hp: CappedValue::new_health(100),
-            ram: CappedValue::new_ram(50, 100),
-            credits: 0,
-            xp: 0,
-            upgrades: upgrades,
-            flags: vec![],
-            buffs: BuffContainer::new(),
-            quests: default_quests(),
-        }
-    }
-}
-
-fn random_default_name() -> String {
+fn random_default_name(rng: &mut impl Rng) -> String {
     let vs: Vec<&str> = vec![
         "riftrunner",
         "astralByte",
@@ -171,5 +383,5 @@ fn random_default_name() -> String {
         "Prime Function",
         "Nexus-11",
     ];
-    return vs.choose(&mut thread_rng()).unwrap().to_string();
+    vs.choose(rng).unwrap().to_string()
 }
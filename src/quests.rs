@@ -1,90 +1,407 @@
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::sync::OnceLock;
 
-#[derive(PartialEq, Hash, Eq)]
-pub enum QuestID {
-    CombatVictory,
-    DatamineSuccess,
+use crate::abilities::AbilityType;
+use crate::buffs::BuffType;
+use crate::conversation::Condition;
+use crate::engine::Tasks;
+use crate::events::GameEvent;
+use crate::items::ItemType;
+use crate::pieces::Networks;
+use crate::player::{Player, PlayerFlag, PlayerUpgradeType};
+
+pub type QuestID = String;
+
+/// where the writers keep the quest list, built into the game from there
+pub const QUESTS_PATH: &str = "content/quests.ron";
+
+/// built in rather than read at startup, so every run and replay sees the same quests
+pub const BUILT_IN_QUESTS: &str = include_str!("../content/quests.ron");
+
+/// One step of a quest, worked through in order
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Objective {
+    /// defeat contacts on one network, or on any if `net` is left out
+    Kill {
+        count: i32,
+        net: Option<Networks>,
+    },
+    /// finish a task successfully
+    Task {
+        task: Tasks,
+        count: i32,
+    },
+    /// credits earned once this objective comes up; spending doesn't count against it and
+    /// quest rewards don't count towards it
+    EarnCredits(i32),
+    IntelLevel {
+        net: Networks,
+        level: i32,
+    },
+    VisitShop,
+    /// see a conversation through to the end, by id
+    FinishConversation(String),
 }
 
-impl QuestID {
-    fn name(&self) -> String {
+impl Objective {
+    /// progress needed to finish the objective
+    pub fn target(&self) -> i32 {
         match self {
-            QuestID::CombatVictory => "Win in Combat".to_string(),
-            QuestID::DatamineSuccess => "Successfully datamine".to_string(),
+            Objective::Kill { count, .. } | Objective::Task { count, .. } => *count,
+            Objective::EarnCredits(amount) => *amount,
+            Objective::IntelLevel { level, .. } => *level,
+            Objective::VisitShop | Objective::FinishConversation(_) => 1,
+        }
+    }
+
+    /// the objective's progress after `event`, given what it was before
+    fn progress(&self, progress: i32, event: &GameEvent) -> i32 {
+        match (self, event) {
+            (Objective::Kill { net, .. }, GameEvent::ContactDefeated { net: killed_on, .. })
+                if net.as_ref().is_none_or(|net| net == killed_on) =>
+            {
+                progress + 1
+            }
+            (Objective::Task { task, .. }, GameEvent::TaskSucceeded { task: done, .. })
+                if task == done =>
+            {
+                progress + 1
+            }
+            (Objective::EarnCredits(_), GameEvent::CreditsEarned(amount)) => progress + amount,
+            (Objective::IntelLevel { net, .. }, GameEvent::IntelLevel { net: on, level })
+                if net == on =>
+            {
+                progress.max(*level)
+            }
+            (Objective::VisitShop, GameEvent::ShopVisited) => 1,
+            (Objective::FinishConversation(id), GameEvent::ConversationFinished(finished))
+                if id == finished =>
+            {
+                1
+            }
+            _ => progress,
         }
     }
 }
 
+impl fmt::Display for Objective {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Objective::Kill { count, net: None } => write!(f, "Defeat {} contacts", count),
+            Objective::Kill {
+                count,
+                net: Some(net),
+            } => write!(f, "Defeat {} contacts on {}", count, net),
+            Objective::Task { task, count } => write!(f, "{} {}x", task.name(), count),
+            Objective::EarnCredits(amount) => write!(f, "Earn {} credits", amount),
+            Objective::IntelLevel { net, level } => {
+                write!(f, "Reach intel level {} on {}", level, net)
+            }
+            Objective::VisitShop => write!(f, "Visit the shop"),
+            Objective::FinishConversation(_) => write!(f, "Hear someone out"),
+        }
+    }
+}
+
+/// What finishing a quest hands out, a quest can grant several
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum QuestReward {
     XP(i32),
+    Credits(i32),
+    Item(ItemType, u32),
+    Buff(BuffType, u32),
+    /// stock an upgrade in the shop
+    Upgrade(PlayerUpgradeType),
+    Flag(PlayerFlag),
+    Network(Networks),
+    /// add an ability to the combat bar
+    Ability(AbilityType),
+}
+
+impl fmt::Display for QuestReward {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            QuestReward::XP(amount) => write!(f, "{} XP", amount),
+            QuestReward::Credits(amount) => write!(f, "{} credits", amount),
+            QuestReward::Item(item, 1) => write!(f, "a {}", item.name()),
+            QuestReward::Item(item, count) => write!(f, "{}x {}", count, item.name()),
+            QuestReward::Buff(buff, duration) => {
+                write!(f, "{} for {} turns", buff.name(), duration)
+            }
+            QuestReward::Upgrade(upgrade) => write!(f, "'{}' in the shop", upgrade.name()),
+            QuestReward::Flag(flag) => write!(f, "{:?}", flag),
+            QuestReward::Network(net) => write!(f, "access to {}", net),
+            QuestReward::Ability(ability) => write!(f, "the {} ability", ability.name()),
+        }
+    }
+}
+
+/// rewards as one line, for the terminal and the quest log
+pub fn reward_summary(rewards: &[QuestReward]) -> String {
+    rewards
+        .iter()
+        .map(|reward| reward.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// One quest as written in the content file
+#[derive(Clone, Serialize, Deserialize)]
+pub struct QuestDef {
+    pub id: QuestID,
+    pub name: String,
+    /// shown in the quest log
+    #[serde(default)]
+    pub description: String,
+    pub objectives: Vec<Objective>,
+    pub rewards: Vec<QuestReward>,
+    /// the quest shows up but can't make progress until these hold
+    #[serde(default)]
+    pub prerequisites: Vec<Condition>,
+    /// hidden until another quest or a conversation starts it
+    #[serde(default)]
+    pub locked: bool,
+    /// quests started once this one is finished
+    #[serde(default)]
+    pub follow_ups: Vec<QuestID>,
 }
 
+/// Something a writer needs to fix in the quest list
+#[derive(Debug)]
+pub enum QuestProblem {
+    DuplicateId(QuestID),
+    NoObjectives(QuestID),
+    UnknownFollowUp { quest: QuestID, follow_up: QuestID },
+}
+
+impl fmt::Display for QuestProblem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            QuestProblem::DuplicateId(id) => write!(f, "quest id '{}' is used twice", id),
+            QuestProblem::NoObjectives(id) => write!(f, "quest '{}' has no objectives", id),
+            QuestProblem::UnknownFollowUp { quest, follow_up } => write!(
+                f,
+                "quest '{}' is followed up by missing quest '{}'",
+                quest, follow_up
+            ),
+        }
+    }
+}
+
+/// parse a quest list, as found in `QUESTS_PATH`
+pub fn parse_quests(text: &str) -> Result<Vec<QuestDef>, ron::error::SpannedError> {
+    ron::from_str(text)
+}
+
+/// everything wrong with a quest list, empty if it's good to go
+pub fn validate_quests(defs: &[QuestDef]) -> Vec<QuestProblem> {
+    let mut problems = vec![];
+    let mut ids = BTreeSet::new();
+    for def in defs {
+        if !ids.insert(def.id.as_str()) {
+            problems.push(QuestProblem::DuplicateId(def.id.clone()));
+        }
+        if def.objectives.is_empty() {
+            problems.push(QuestProblem::NoObjectives(def.id.clone()));
+        }
+    }
+    for def in defs {
+        for follow_up in def.follow_ups.iter() {
+            if !ids.contains(follow_up.as_str()) {
+                problems.push(QuestProblem::UnknownFollowUp {
+                    quest: def.id.clone(),
+                    follow_up: follow_up.clone(),
+                });
+            }
+        }
+    }
+    problems
+}
+
+/// every quest in the game, in the order they're written
+pub fn quest_defs() -> &'static [QuestDef] {
+    static DEFS: OnceLock<Vec<QuestDef>> = OnceLock::new();
+    DEFS.get_or_init(|| {
+        parse_quests(BUILT_IN_QUESTS).expect("built-in quests parse, see --check-content")
+    })
+}
+
+pub fn quest_def(id: &str) -> Option<&'static QuestDef> {
+    quest_defs().iter().find(|def| def.id == id)
+}
+
+/// the quest's display name, or its id if it has gone missing from the content
+pub fn quest_name(id: &str) -> String {
+    quest_def(id)
+        .map(|def| def.name.clone())
+        .unwrap_or_else(|| id.to_string())
+}
+
+pub fn default_quests() -> BTreeMap<QuestID, Quest> {
+    quest_defs()
+        .iter()
+        .map(|def| {
+            let quest = Quest {
+                quest_id: def.id.clone(),
+                step: 0,
+                progress: 0,
+                visible: !def.locked,
+                active: !def.locked && def.prerequisites.is_empty(),
+                tracked: true,
+                completed_turn: None,
+                reward_claimed: false,
+            };
+            (def.id.clone(), quest)
+        })
+        .collect()
+}
+
+#[derive(Serialize, Deserialize)]
 pub struct Quest {
     pub quest_id: QuestID,
-    /// value must be at or above this value to finish the quest
-    finish_threshold: u32,
-    pub reward: QuestReward,
     // state
-    /// quest will complete once this value is above finish_threshold
-    value: u32,
+    /// index of the objective being worked on, past the last one once finished
+    step: usize,
+    /// progress on the current objective
+    progress: i32,
     /// quest can be tracked, may or may not be
     visible: bool,
     /// progress can be made
     active: bool,
     /// quest is actively being tracked
     pub tracked: bool,
-}
-
-pub fn default_quests() -> HashMap<QuestID, Quest> {
-    let mut quests = HashMap::new();
-    quests.insert(
-        QuestID::CombatVictory,
-        Quest {
-            quest_id: QuestID::CombatVictory,
-            finish_threshold: 4,
-            reward: QuestReward::XP(80),
-            value: 0,
-            visible: true,
-            active: true,
-            tracked: true,
-        },
-    );
-    quests.insert(
-        QuestID::DatamineSuccess,
-        Quest {
-            quest_id: QuestID::DatamineSuccess,
-            finish_threshold: 10,
-            reward: QuestReward::XP(100),
-            value: 0,
-            visible: true,
-            active: true,
-            tracked: true,
-        },
-    );
-    return quests;
+    completed_turn: Option<i32>,
+    reward_claimed: bool,
 }
 
 impl Quest {
+    pub fn def(&self) -> Option<&'static QuestDef> {
+        quest_def(&self.quest_id)
+    }
+
+    /// the objective being worked on, `None` once finished
+    pub fn objective(&self) -> Option<&'static Objective> {
+        self.def()?.objectives.get(self.step)
+    }
+
+    /// how many objectives are done
+    pub fn step(&self) -> usize {
+        self.step
+    }
+
+    /// (progress, target) on the current objective
+    pub fn objective_progress(&self) -> Option<(i32, i32)> {
+        self.objective()
+            .map(|objective| (self.progress.min(objective.target()), objective.target()))
+    }
+
     pub fn name(&self) -> String {
-        format!(
-            "{}x {}",
-            self.finish_threshold - self.value,
-            self.quest_id.name()
-        )
+        let name = quest_name(&self.quest_id);
+        match self.objective() {
+            Some(_) if !self.active => format!("{} - locked", name),
+            Some(objective) if objective.target() > 1 => format!(
+                "{}: {} ({}/{})",
+                name,
+                objective,
+                self.progress.min(objective.target()),
+                objective.target()
+            ),
+            Some(objective) => format!("{}: {}", name, objective),
+            None => name,
+        }
+    }
+
+    /// make the quest visible; it makes progress once its prerequisites hold
+    pub fn start(&mut self) {
+        self.visible = true;
     }
 
-    /// increment value if active
-    pub fn increment(&mut self) {
-        if self.active {
-            self.value += 1;
+    pub fn is_started(&self) -> bool {
+        self.visible
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    pub fn prerequisites_met(&self, player: &Player) -> bool {
+        self.def().is_some_and(|def| {
+            def.prerequisites
+                .iter()
+                .all(|condition| condition.met(player))
+        })
+    }
+
+    /// let the quest start making progress
+    pub fn activate(&mut self) {
+        self.active = true;
+    }
+
+    /// feed an event to the current objective, true if that finished it
+    pub fn record(&mut self, event: &GameEvent) -> bool {
+        let Some(objective) = self.objective() else {
+            return false;
         };
+        if !self.active {
+            return false;
+        }
+        self.progress = objective.progress(self.progress, event);
+        if self.progress >= objective.target() {
+            self.complete_objective();
+            return true;
+        }
+        false
+    }
+
+    /// skip straight past the current objective
+    pub fn complete_objective(&mut self) {
+        if self.objective().is_some() {
+            self.step += 1;
+            self.progress = 0;
+        }
     }
 
     pub fn is_finished(&self) -> bool {
-        self.value >= self.finish_threshold
+        self.def()
+            .is_none_or(|def| self.step >= def.objectives.len())
+    }
+
+    pub fn completed_turn(&self) -> Option<i32> {
+        self.completed_turn
+    }
+
+    pub fn mark_completed(&mut self, turn: i32) {
+        self.completed_turn = Some(turn);
+    }
+
+    /// finished, but the rewards haven't been handed out yet
+    pub fn reward_waiting(&self) -> bool {
+        self.is_finished() && !self.reward_claimed
+    }
+
+    pub fn claim_reward(&mut self) {
+        self.reward_claimed = true;
     }
 
     pub fn trackable(&self) -> bool {
-        return self.visible && !self.is_finished();
+        self.visible && !self.is_finished()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_built_in_quests_parse_and_validate() {
+        let defs = parse_quests(BUILT_IN_QUESTS).expect("built-in quests parse");
+        let problems = validate_quests(&defs)
+            .iter()
+            .map(|problem| problem.to_string())
+            .collect::<Vec<_>>();
+        assert!(problems.is_empty(), "{:?}", problems);
+        assert_eq!(quest_defs().len(), defs.len());
     }
 }
@@ -0,0 +1,57 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use crate::save::{SaveError, SaveHeader};
+
+/// bump this whenever `RunRecord` changes
+pub const RECORDS_VERSION: u32 = 1;
+pub const RECORDS_PATH: &str = "netrunner_runs.ron";
+
+/// How an ironman run ended, kept after the run itself is gone
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RunRecord {
+    pub seed: u64,
+    pub player_name: String,
+    pub turns: i32,
+    pub level: i32,
+    pub kills: u32,
+    pub credits: i32,
+    /// whatever the player was fighting when they flatlined
+    pub killed_by: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct RecordsFile {
+    version: u32,
+    runs: Vec<RunRecord>,
+}
+
+/// every finished run so far, oldest first; no file just means no runs yet
+pub fn load_records(path: &Path) -> Result<Vec<RunRecord>, SaveError> {
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    let text = fs::read_to_string(path)?;
+    let header: SaveHeader = ron::from_str(&text)?;
+    if header.version != RECORDS_VERSION {
+        return Err(SaveError::VersionMismatch {
+            found: header.version,
+            expected: RECORDS_VERSION,
+        });
+    }
+    let records: RecordsFile = ron::from_str(&text)?;
+    Ok(records.runs)
+}
+
+pub fn record_run(record: RunRecord, path: &Path) -> Result<(), SaveError> {
+    let mut runs = load_records(path)?;
+    runs.push(record);
+    let records = RecordsFile {
+        version: RECORDS_VERSION,
+        runs,
+    };
+    let text = ron::ser::to_string_pretty(&records, ron::ser::PrettyConfig::default())?;
+    fs::write(path, text)?;
+    Ok(())
+}
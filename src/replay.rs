@@ -0,0 +1,186 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::engine::{Action, ActionError, Game, GameSettings, Outcome};
+use crate::save::{SaveError, SaveHeader};
+
+/// bump this whenever `Action` or the replay layout changes
+pub const REPLAY_VERSION: u32 = 28;
+pub const REPLAY_PATH: &str = "netrunner_replay.ron";
+
+/// One accepted player action, in the order it was taken
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LoggedAction {
+    /// the turn the action was taken on
+    pub turn: i32,
+    /// real time that passed before the action, for idle intel
+    pub idle_seconds: f32,
+    pub action: Action,
+}
+
+/// A seed plus every action taken is enough to rebuild a whole run
+#[derive(Serialize, Deserialize)]
+pub struct Replay {
+    pub version: u32,
+    pub seed: u64,
+    pub settings: GameSettings,
+    pub actions: Vec<LoggedAction>,
+}
+
+/// The replay stopped matching what was recorded
+#[derive(Debug)]
+pub enum ReplayError {
+    TurnMismatch {
+        step: usize,
+        recorded: i32,
+        actual: i32,
+    },
+    Rejected {
+        step: usize,
+        err: ActionError,
+    },
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReplayError::TurnMismatch {
+                step,
+                recorded,
+                actual,
+            } => write!(
+                f,
+                "replay out of sync at step {}: recorded on turn {}, but the game is on turn {}",
+                step, recorded, actual
+            ),
+            ReplayError::Rejected { step, err } => {
+                write!(f, "replay out of sync at step {}: {}", step, err)
+            }
+        }
+    }
+}
+
+pub fn save_replay(replay: &Replay, path: &Path) -> Result<(), SaveError> {
+    let text = ron::ser::to_string_pretty(replay, ron::ser::PrettyConfig::default())?;
+    fs::write(path, text)?;
+    Ok(())
+}
+
+pub fn load_replay(path: &Path) -> Result<Replay, SaveError> {
+    let text = fs::read_to_string(path)?;
+    let header: SaveHeader = ron::from_str(&text)?;
+    if header.version != REPLAY_VERSION {
+        return Err(SaveError::VersionMismatch {
+            found: header.version,
+            expected: REPLAY_VERSION,
+        });
+    }
+    Ok(ron::from_str(&text)?)
+}
+
+/// Plays a recorded run back one action at a time
+pub struct Replayer {
+    seed: u64,
+    settings: GameSettings,
+    actions: Vec<LoggedAction>,
+    next: usize,
+}
+
+impl Replayer {
+    pub fn new(replay: Replay) -> Self {
+        Self {
+            seed: replay.seed,
+            settings: replay.settings,
+            actions: replay.actions,
+            next: 0,
+        }
+    }
+
+    /// the game as it was before the first recorded action
+    pub fn new_game(&self) -> Game {
+        Game::with_settings(self.seed, self.settings.clone())
+    }
+
+    /// the upcoming action, if there is one
+    pub fn peek(&self) -> Option<&LoggedAction> {
+        self.actions.get(self.next)
+    }
+
+    /// apply the next recorded action to `game`, `None` once the replay is over
+    pub fn step(&mut self, game: &mut Game) -> Option<Result<Vec<Outcome>, ReplayError>> {
+        let logged = self.actions.get(self.next)?.clone();
+        let step = self.next;
+        self.next += 1;
+        if logged.turn != game.turn {
+            return Some(Err(ReplayError::TurnMismatch {
+                step,
+                recorded: logged.turn,
+                actual: game.turn,
+            }));
+        }
+        game.accrue_intel(logged.idle_seconds);
+        Some(
+            game.apply(logged.action)
+                .map_err(|err| ReplayError::Rejected { step, err }),
+        )
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next >= self.actions.len()
+    }
+
+    /// (actions played, total actions)
+    pub fn progress(&self) -> (usize, usize) {
+        (self.next, self.actions.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::abilities::AbilityType;
+    use crate::engine::Tasks;
+
+    #[test]
+    fn a_saved_replay_rebuilds_the_run() {
+        let mut game = Game::new(99);
+        let hack = Action::UseAbility {
+            ability: AbilityType::Hack,
+            target: 0,
+        };
+        for _ in 0..12 {
+            game.accrue_intel(4.0);
+            for action in [
+                Action::DoTask(Tasks::Datamine),
+                hack.clone(),
+                Action::Respawn,
+            ] {
+                let _ = game.apply(action);
+            }
+        }
+        let path =
+            std::env::temp_dir().join(format!("netrunner_replay_{}.ron", std::process::id()));
+        save_replay(&game.replay(), &path).unwrap();
+        let replay = load_replay(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let mut replayer = Replayer::new(replay);
+        let mut rebuilt = replayer.new_game();
+        while let Some(result) = replayer.step(&mut rebuilt) {
+            result.unwrap();
+        }
+        assert!(replayer.is_finished());
+        // idle time after the last accepted action isn't part of the replay, the rest is
+        assert_eq!(rebuilt.turn, game.turn);
+        assert_eq!(
+            ron::to_string(&rebuilt.player).unwrap(),
+            ron::to_string(&game.player).unwrap()
+        );
+        assert_eq!(
+            ron::to_string(&rebuilt.activity).unwrap(),
+            ron::to_string(&game.activity).unwrap()
+        );
+    }
+}
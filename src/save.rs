@@ -0,0 +1,138 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::engine::Game;
+
+/// bump this whenever the layout of the saved game state changes
+pub const SAVE_VERSION: u32 = 19;
+pub const SAVE_PATH: &str = "netrunner_save.ron";
+
+#[derive(Serialize, Deserialize)]
+struct SaveFile<G, T> {
+    version: u32,
+    game: G,
+    /// the front end's terminal history, so it survives a restart too
+    terminal_lines: T,
+}
+
+/// only the header of a save or replay file, so the version can be checked
+/// before trying to make sense of the rest of it
+#[derive(Deserialize)]
+pub(crate) struct SaveHeader {
+    pub(crate) version: u32,
+}
+
+/// Anything that can go wrong reading or writing a save or replay file
+#[derive(Debug)]
+pub enum SaveError {
+    Io(io::Error),
+    Serialize(ron::Error),
+    Deserialize(ron::error::SpannedError),
+    VersionMismatch { found: u32, expected: u32 },
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SaveError::Io(err) => write!(f, "could not access file: {}", err),
+            SaveError::Serialize(err) => write!(f, "could not write data: {}", err),
+            SaveError::Deserialize(err) => write!(f, "file is corrupted: {}", err),
+            SaveError::VersionMismatch { found, expected } => write!(
+                f,
+                "file is version {}, but this build expects version {}",
+                found, expected
+            ),
+        }
+    }
+}
+
+impl From<io::Error> for SaveError {
+    fn from(err: io::Error) -> Self {
+        SaveError::Io(err)
+    }
+}
+
+impl From<ron::Error> for SaveError {
+    fn from(err: ron::Error) -> Self {
+        SaveError::Serialize(err)
+    }
+}
+
+impl From<ron::error::SpannedError> for SaveError {
+    fn from(err: ron::error::SpannedError) -> Self {
+        SaveError::Deserialize(err)
+    }
+}
+
+pub fn save_game(game: &Game, terminal_lines: &[String], path: &Path) -> Result<(), SaveError> {
+    let save = SaveFile {
+        version: SAVE_VERSION,
+        game,
+        terminal_lines,
+    };
+    let text = ron::ser::to_string_pretty(&save, ron::ser::PrettyConfig::default())?;
+    fs::write(path, text)?;
+    Ok(())
+}
+
+/// move a save that can't be loaded out of the way, so a new run doesn't overwrite it;
+/// returns where it went
+pub fn set_aside(path: &Path) -> io::Result<PathBuf> {
+    let mut backup = path.as_os_str().to_owned();
+    backup.push(".bak");
+    let backup = PathBuf::from(backup);
+    fs::rename(path, &backup)?;
+    Ok(backup)
+}
+
+pub fn load_game(path: &Path) -> Result<(Game, Vec<String>), SaveError> {
+    let text = fs::read_to_string(path)?;
+    let header: SaveHeader = ron::from_str(&text)?;
+    if header.version != SAVE_VERSION {
+        return Err(SaveError::VersionMismatch {
+            found: header.version,
+            expected: SAVE_VERSION,
+        });
+    }
+    let save: SaveFile<Game, Vec<String>> = ron::from_str(&text)?;
+    Ok((save.game, save.terminal_lines))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::abilities::AbilityType;
+    use crate::engine::{Action, Tasks};
+
+    #[test]
+    fn a_loaded_game_rolls_the_same_as_the_original() {
+        let path = std::env::temp_dir().join(format!("netrunner_save_{}.ron", std::process::id()));
+        let mut game = Game::new(42);
+        for _ in 0..5 {
+            let _ = game.apply(Action::DoTask(Tasks::Search));
+        }
+        let lines = vec!["a line".to_string()];
+        save_game(&game, &lines, &path).unwrap();
+        let (mut loaded, loaded_lines) = load_game(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded_lines, lines);
+        assert_eq!(loaded.turn, game.turn);
+        let hack = Action::UseAbility {
+            ability: AbilityType::Hack,
+            target: 0,
+        };
+        for _ in 0..10 {
+            for action in [
+                Action::DoTask(Tasks::Datamine),
+                hack.clone(),
+                Action::Escape,
+            ] {
+                assert_eq!(loaded.apply(action.clone()).ok(), game.apply(action).ok());
+            }
+        }
+    }
+}
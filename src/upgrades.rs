@@ -0,0 +1,114 @@
+use crate::pieces::SkillType;
+use crate::player::PlayerUpgradeType;
+
+/// What one level of an upgrade does to the player
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UpgradeEffect {
+    MaxHp(i32),
+    MaxRam(i32),
+    SkillCap(SkillType, i32),
+    /// RAM regenerated every turn
+    RamRegen(i32),
+}
+
+impl UpgradeEffect {
+    pub fn description(&self) -> String {
+        match self {
+            UpgradeEffect::MaxHp(amount) => format!("+{} max HP", amount),
+            UpgradeEffect::MaxRam(amount) => format!("+{} max RAM", amount),
+            UpgradeEffect::SkillCap(skill, amount) => format!("+{} {} cap", amount, skill),
+            UpgradeEffect::RamRegen(amount) => format!("+{} RAM per turn", amount),
+        }
+    }
+}
+
+/// Everything the shop needs to know about an upgrade
+pub struct UpgradeDef {
+    pub upgrade_type: PlayerUpgradeType,
+    pub name: &'static str,
+    base_cost: u32,
+    cost_per_level: u32,
+    pub max_level: u32,
+    /// applied once per level bought
+    pub effects: &'static [UpgradeEffect],
+    /// other upgrades that must reach a level before this one goes on sale
+    pub prerequisites: &'static [(PlayerUpgradeType, u32)],
+    /// stays off the shelves until the player knows the network this well
+    pub min_intel_level: i32,
+}
+
+impl UpgradeDef {
+    /// cost of buying the next level when the upgrade is at `level`
+    pub fn cost(&self, level: u32) -> u32 {
+        self.base_cost + (level * self.cost_per_level)
+    }
+
+    pub fn description(&self) -> String {
+        self.effects
+            .iter()
+            .map(|effect| effect.description())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// The shop's full stock list, in the order the upgrades are shown
+pub const CATALOG: &[UpgradeDef] = &[
+    UpgradeDef {
+        upgrade_type: PlayerUpgradeType::HPMaxUp,
+        name: "HP Max +",
+        base_cost: 100,
+        cost_per_level: 50,
+        max_level: 5,
+        effects: &[UpgradeEffect::MaxHp(50)],
+        prerequisites: &[],
+        min_intel_level: 0,
+    },
+    UpgradeDef {
+        upgrade_type: PlayerUpgradeType::RamMaxUp,
+        name: "RAM Max +",
+        base_cost: 120,
+        cost_per_level: 60,
+        max_level: 5,
+        effects: &[UpgradeEffect::MaxRam(25)],
+        prerequisites: &[],
+        min_intel_level: 0,
+    },
+    UpgradeDef {
+        upgrade_type: PlayerUpgradeType::SecurityUp,
+        name: "Sec Max +",
+        base_cost: 150,
+        cost_per_level: 300,
+        max_level: 3,
+        effects: &[UpgradeEffect::SkillCap(SkillType::Security, 2)],
+        prerequisites: &[(PlayerUpgradeType::HPMaxUp, 1)],
+        min_intel_level: 1,
+    },
+    UpgradeDef {
+        upgrade_type: PlayerUpgradeType::HackingUp,
+        name: "Hack Max +",
+        base_cost: 150,
+        cost_per_level: 300,
+        max_level: 3,
+        effects: &[UpgradeEffect::SkillCap(SkillType::Hacking, 2)],
+        prerequisites: &[(PlayerUpgradeType::RamMaxUp, 1)],
+        min_intel_level: 1,
+    },
+    UpgradeDef {
+        upgrade_type: PlayerUpgradeType::BackgroundDaemon,
+        name: "Background Daemon",
+        base_cost: 250,
+        cost_per_level: 250,
+        max_level: 3,
+        effects: &[UpgradeEffect::RamRegen(1)],
+        prerequisites: &[(PlayerUpgradeType::RamMaxUp, 2)],
+        min_intel_level: 2,
+    },
+];
+
+pub fn upgrade_def(upgrade_type: &PlayerUpgradeType) -> &'static UpgradeDef {
+    CATALOG
+        .iter()
+        .find(|def| def.upgrade_type == *upgrade_type)
+        .expect("every upgrade type has a catalog entry")
+}
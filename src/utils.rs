@@ -1,8 +1,31 @@
-// use rand::seq::SliceRandom;
-use rand::{thread_rng, Rng};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::path::PathBuf;
 
-pub fn roll_encounter(success_chance: f32) -> bool {
+/// The one source of randomness for a run; the same seed replays the same run
+pub type GameRng = ChaCha8Rng;
+
+pub fn seeded_rng(seed: u64) -> GameRng {
+    GameRng::seed_from_u64(seed)
+}
+
+pub fn roll_encounter(rng: &mut impl Rng, success_chance: f32) -> bool {
     // true if successful - chance is between 0 and 1
-    let roll: f32 = thread_rng().gen();
-    return roll > success_chance;
+    let roll: f32 = rng.gen();
+    roll > success_chance
+}
+
+/// a content path like `content/quests.ron`, looked up beside the executable and then in the
+/// folders above it, so both a shipped game and a cargo build find their files from anywhere;
+/// relative to the working directory if none of those have it
+pub fn content_path(relative: &str) -> PathBuf {
+    std::env::current_exe()
+        .ok()
+        .and_then(|exe| {
+            exe.ancestors()
+                .skip(1)
+                .map(|dir| dir.join(relative))
+                .find(|path| path.exists())
+        })
+        .unwrap_or_else(|| PathBuf::from(relative))
 }